@@ -0,0 +1,916 @@
+//! Generates http-typed `Request` implementations from an OpenAPI 3 document,
+//! in json or yaml. The generated code contains:
+//! - a struct or enum for each schema in `components.schemas`
+//! - a request struct for each operation, implementing `Request` and `Route`
+//! - a `request_group!` containing every request
+//!
+//! It depends on `http-typed` and `serde` with the `derive` feature.
+//!
+//! In a build script:
+//!
+//! ```ignore
+//! fn main() {
+//!     let out_dir = std::env::var("OUT_DIR").unwrap();
+//!     http_typed_codegen::Generator::new()
+//!         .group("Petstore")
+//!         .generate_file("petstore.yaml", format!("{out_dir}/petstore.rs"))
+//!         .unwrap();
+//! }
+//! ```
+//!
+//! ```ignore
+//! include!(concat!(env!("OUT_DIR"), "/petstore.rs"));
+//! ```
+//!
+//! Or from the command line, with the `http-typed-codegen` binary:
+//!
+//! ```text
+//! http-typed-codegen petstore.yaml --group Petstore --output src/petstore.rs
+//! ```
+//!
+//! Operations that cannot be represented are skipped, and listed in a comment
+//! at the top of the generated file. This includes request bodies that are not
+//! json or form-urlencoded. Operations with both a request body and query
+//! parameters use a generated `BodyAndQuery` serializer, which sends the `body`
+//! field in the body and the other fields in the query string.
+//!
+//! Request types only derive `Serialize` by default. `server` adds
+//! `Deserialize`, for use with `http_typed::Routes`, to the requests that can
+//! be rebuilt from the path, query string and body. Requests with both a body
+//! and either path or query parameters, or with required header parameters,
+//! cannot. `json_schema` adds `schemars::JsonSchema` to every type, for use
+//! with `http_typed::OpenApi`, and needs a dependency on `schemars`. `OpenApi`
+//! cannot describe the requests that use `BodyAndQuery`, so a group that
+//! contains them must be described one request at a time with
+//! `OpenApi::request`.
+
+use std::{collections::BTreeSet, path::Path};
+
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{format_ident, quote};
+use serde_json::{Map, Value};
+
+/// Generates Rust source code from an OpenAPI document.
+#[derive(Debug, Clone)]
+pub struct Generator {
+    group: String,
+    server: bool,
+    json_schema: bool,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse the document as json or yaml: {0}")]
+    Parse(#[from] serde_yaml::Error),
+    #[error("invalid OpenAPI document: {0}")]
+    InvalidSpec(String),
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Self {
+            group: "Api".into(),
+            server: false,
+            json_schema: false,
+        }
+    }
+}
+
+impl Generator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Name of the request group containing every request. Defaults to "Api".
+    pub fn group(mut self, name: impl Into<String>) -> Self {
+        self.group = name.into();
+        self
+    }
+
+    /// Derive `Deserialize` for the request types that a server can rebuild
+    /// from an http request. Disabled by default.
+    pub fn server(mut self, server: bool) -> Self {
+        self.server = server;
+        self
+    }
+
+    /// Derive `schemars::JsonSchema` for every generated type. Disabled by
+    /// default.
+    pub fn json_schema(mut self, json_schema: bool) -> Self {
+        self.json_schema = json_schema;
+        self
+    }
+
+    /// Generate the source code for the OpenAPI document, which may be json
+    /// or yaml.
+    pub fn generate(&self, spec: &str) -> Result<String, Error> {
+        let spec: Value = match serde_json::from_str(spec) {
+            Ok(spec) => spec,
+            Err(_) => serde_yaml::from_str(spec)?,
+        };
+        match spec["openapi"].as_str() {
+            Some(version) if version.starts_with('3') => (),
+            _ => return Err(Error::InvalidSpec("only OpenAPI 3 is supported".into())),
+        }
+        let mut context = Context::new(&spec, self);
+        let tokens = context.file(&self.group)?;
+        let file: syn::File = syn::parse2(tokens)
+            .map_err(|error| Error::InvalidSpec(format!("generated invalid code: {error}")))?;
+
+        let mut source = String::from("// Generated by http-typed-codegen. Do not edit.\n");
+        if !context.skipped.is_empty() {
+            source.push_str("//\n// Skipped operations:\n");
+            for skipped in &context.skipped {
+                source.push_str(&format!("// - {skipped}\n"));
+            }
+        }
+        source.push('\n');
+        source.push_str(&prettyplease::unparse(&file));
+        Ok(source)
+    }
+
+    /// Generate the source code for the OpenAPI document at `spec_path` and
+    /// write it to `output_path`. In a build script, this also tells cargo to
+    /// rerun the build script when the document changes.
+    pub fn generate_file(
+        &self,
+        spec_path: impl AsRef<Path>,
+        output_path: impl AsRef<Path>,
+    ) -> Result<(), Error> {
+        let spec_path = spec_path.as_ref();
+        // only build scripts have both of these
+        if std::env::var_os("OUT_DIR").is_some() && std::env::var_os("TARGET").is_some() {
+            println!("cargo:rerun-if-changed={}", spec_path.display());
+        }
+        let source = self.generate(&std::fs::read_to_string(spec_path)?)?;
+        std::fs::write(output_path, source)?;
+        Ok(())
+    }
+}
+
+struct Context<'a> {
+    spec: &'a Value,
+    server: bool,
+    /// Extra derives for every generated type
+    derives: TokenStream,
+    items: Vec<TokenStream>,
+    requests: Vec<Ident>,
+    /// Type names that are already used
+    names: BTreeSet<String>,
+    /// Descriptions of the operations that could not be generated
+    skipped: Vec<String>,
+    /// The serializer for requests with both a body and query parameters,
+    /// once it is needed
+    body_and_query: Option<Ident>,
+}
+
+/// A request parameter that becomes a field of the request struct.
+struct Param {
+    name: String,
+    location: String,
+    field: Ident,
+    ty: TokenStream,
+    required: bool,
+    doc: Option<String>,
+}
+
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+impl<'a> Context<'a> {
+    fn new(spec: &'a Value, generator: &Generator) -> Self {
+        Self {
+            spec,
+            server: generator.server,
+            derives: match generator.json_schema {
+                true => quote!(, ::schemars::JsonSchema),
+                false => quote!(),
+            },
+            items: vec![],
+            requests: vec![],
+            names: BTreeSet::new(),
+            skipped: vec![],
+            body_and_query: None,
+        }
+    }
+
+    fn file(&mut self, group: &str) -> Result<TokenStream, Error> {
+        let schemas = self
+            .spec
+            .pointer("/components/schemas")
+            .and_then(Value::as_object);
+        for name in schemas.into_iter().flat_map(Map::keys) {
+            self.names.insert(type_name(name));
+        }
+        for (name, schema) in schemas.into_iter().flatten() {
+            let item = self.schema_item(&type_ident(name), schema);
+            self.items.push(item);
+        }
+
+        let paths = self.spec["paths"].as_object();
+        for (path, item) in paths.into_iter().flatten() {
+            let item = self.resolve(item);
+            for method in METHODS {
+                if let Some(operation) = item.get(method) {
+                    self.operation(path, method, item, operation)?;
+                }
+            }
+        }
+
+        let items = &self.items;
+        let requests = &self.requests;
+        let group = type_ident(group);
+        Ok(quote! {
+            #(#items)*
+
+            ::http_typed::request_group!(pub #group { #(#requests),* });
+        })
+    }
+
+    /// Follows a `$ref` to a location in the same document.
+    fn resolve(&self, mut value: &'a Value) -> &'a Value {
+        for _ in 0..16 {
+            match value["$ref"].as_str().and_then(|r| r.strip_prefix('#')) {
+                Some(pointer) => match self.spec.pointer(pointer) {
+                    Some(target) => value = target,
+                    None => break,
+                },
+                None => break,
+            }
+        }
+        value
+    }
+
+    /// A struct, enum, or type alias for a named schema.
+    fn schema_item(&mut self, name: &Ident, schema: &Value) -> TokenStream {
+        let doc = doc_attr(schema["description"].as_str());
+        if let Some(values) = schema["enum"].as_array() {
+            if values.iter().all(Value::is_string) {
+                let mut used = BTreeSet::new();
+                let variants = values.iter().filter_map(Value::as_str).map(|value| {
+                    let mut variant = type_name(value);
+                    if variant.is_empty() || !used.insert(variant.clone()) {
+                        variant = format!("Variant{}", used.len());
+                        used.insert(variant.clone());
+                    }
+                    let variant = format_ident!("{}", variant);
+                    quote! {
+                        #[serde(rename = #value)]
+                        #variant
+                    }
+                });
+                let derives = &self.derives;
+                return quote! {
+                    #doc
+                    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ::serde::Serialize, ::serde::Deserialize #derives)]
+                    pub enum #name {
+                        #(#variants,)*
+                    }
+                };
+            }
+        }
+        if let Some(properties) = schema["properties"].as_object() {
+            let required: Vec<&str> = schema["required"]
+                .as_array()
+                .map(|required| required.iter().filter_map(Value::as_str).collect())
+                .unwrap_or_default();
+            let fields = properties.iter().map(|(property, schema)| {
+                let field = field_ident(property);
+                let rename = rename_attr(property, &field);
+                let doc = doc_attr(schema["description"].as_str());
+                let ty = self.schema_type(schema);
+                if required.contains(&property.as_str()) || self.is_nullable(schema) {
+                    quote! {
+                        #doc
+                        #rename
+                        pub #field: #ty,
+                    }
+                } else {
+                    quote! {
+                        #doc
+                        #rename
+                        #[serde(default, skip_serializing_if = "Option::is_none")]
+                        pub #field: ::std::option::Option<#ty>,
+                    }
+                }
+            });
+            let derives = &self.derives;
+            return quote! {
+                #doc
+                #[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize #derives)]
+                pub struct #name {
+                    #(#fields)*
+                }
+            };
+        }
+        let ty = self.schema_type(schema);
+        quote! {
+            #doc
+            pub type #name = #ty;
+        }
+    }
+
+    /// The Rust type for a schema. Inline objects without additional
+    /// properties are represented as `serde_json::Value`.
+    fn schema_type(&self, schema: &Value) -> TokenStream {
+        let ty = self.non_null_type(schema);
+        if self.is_nullable(schema) {
+            quote!(::std::option::Option<#ty>)
+        } else {
+            ty
+        }
+    }
+
+    fn non_null_type(&self, schema: &Value) -> TokenStream {
+        if let Some(reference) = schema["$ref"].as_str() {
+            return match reference.strip_prefix("#/components/schemas/") {
+                Some(name) => {
+                    let name = type_ident(name);
+                    quote!(#name)
+                }
+                None => self.non_null_type(self.resolve(schema)),
+            };
+        }
+        for combinator in ["allOf", "oneOf", "anyOf"] {
+            if let Some([schema]) = schema[combinator].as_array().map(Vec::as_slice) {
+                return self.non_null_type(schema);
+            }
+        }
+        let format = schema["format"].as_str().unwrap_or_default();
+        match schema["type"].as_str() {
+            Some("string") if format == "binary" => quote!(::std::vec::Vec<u8>),
+            Some("string") => quote!(::std::string::String),
+            Some("integer") => match format {
+                "int32" => quote!(i32),
+                "uint32" => quote!(u32),
+                "uint64" => quote!(u64),
+                _ => quote!(i64),
+            },
+            Some("number") if format == "float" => quote!(f32),
+            Some("number") => quote!(f64),
+            Some("boolean") => quote!(bool),
+            Some("array") => {
+                let item = self.schema_type(&schema["items"]);
+                quote!(::std::vec::Vec<#item>)
+            }
+            Some("object") | None if schema["additionalProperties"].is_object() => {
+                let value = self.schema_type(&schema["additionalProperties"]);
+                quote!(::std::collections::HashMap<::std::string::String, #value>)
+            }
+            _ => quote!(::serde_json::Value),
+        }
+    }
+
+    fn is_nullable(&self, schema: &Value) -> bool {
+        schema["nullable"].as_bool() == Some(true)
+    }
+
+    /// The type of a request or response body. Inline objects get a struct
+    /// named after the operation.
+    fn body_type(&mut self, name: String, schema: &Value) -> TokenStream {
+        if schema["properties"].is_object() {
+            let name = self.unique_name(name);
+            let ident = format_ident!("{}", name);
+            let item = self.schema_item(&ident, schema);
+            self.items.push(item);
+            quote!(#ident)
+        } else {
+            self.schema_type(schema)
+        }
+    }
+
+    fn unique_name(&mut self, name: String) -> String {
+        let mut unique = name.clone();
+        let mut suffix = 2;
+        while !self.names.insert(unique.clone()) {
+            unique = format!("{name}{suffix}");
+            suffix += 1;
+        }
+        unique
+    }
+
+    fn operation(
+        &mut self,
+        path: &str,
+        method: &str,
+        path_item: &Value,
+        operation: &Value,
+    ) -> Result<(), Error> {
+        let description = format!("{} {path}", method.to_ascii_uppercase());
+        let name = match operation["operationId"].as_str() {
+            Some(id) => type_name(id),
+            None => type_name(&description),
+        };
+        let name = match self.names.contains(&name) {
+            true => self.unique_name(format!("{name}Request")),
+            false => self.unique_name(name),
+        };
+        let ident = format_ident!("{}", name);
+
+        let params = self.params(path_item, operation);
+        let mut path_params = vec![];
+        let mut query_params = vec![];
+        let mut header_params = vec![];
+        for param in params {
+            match param.location.as_str() {
+                "path" => path_params.push(param),
+                "query" => query_params.push(param),
+                "header" => header_params.push(param),
+                _ => (),
+            }
+        }
+
+        let Some((format, template, args)) = path_format(path, &path_params)? else {
+            self.skipped.push(format!(
+                "{description}: path parameters are not all defined"
+            ));
+            self.names.remove(&name);
+            return Ok(());
+        };
+
+        let mut body_serializer = quote!(::http_typed::SerdeJson);
+        let body = match operation.get("requestBody").map(|body| self.resolve(body)) {
+            Some(body) => {
+                let content = body["content"].as_object();
+                let find = |matches: fn(&str) -> bool| {
+                    content
+                        .into_iter()
+                        .flatten()
+                        .find(|(media_type, _)| matches(media_type))
+                        .map(|(_, media)| media)
+                };
+                let json = find(|media_type| media_type.contains("json"));
+                let form = find(|media_type| media_type == "application/x-www-form-urlencoded");
+                let media = match (json, form) {
+                    (Some(media), _) => media,
+                    (None, Some(media)) => {
+                        body_serializer = quote!(::http_typed::FormUrlEncoded);
+                        media
+                    }
+                    (None, None) => {
+                        self.skipped.push(format!(
+                            "{description}: request body is not json or form-urlencoded"
+                        ));
+                        self.names.remove(&name);
+                        return Ok(());
+                    }
+                };
+                let ty = self.body_type(format!("{name}Body"), &media["schema"]);
+                let required = body["required"].as_bool() == Some(true);
+                Some(if required {
+                    ty
+                } else {
+                    quote!(::std::option::Option<#ty>)
+                })
+            }
+            None => None,
+        };
+
+        let (response, deserializer) = self.response_type(&name, operation);
+        let error = self.error_type(&name, operation);
+        let mut serializer_impl = None;
+        let serializer = match &body {
+            Some(body) if !query_params.is_empty() => {
+                let serializer = self.body_and_query();
+                serializer_impl = Some(quote! {
+                    impl ::http_typed::SerializeBody<#ident> for #serializer {
+                        type Error = ::std::boxed::Box<
+                            dyn ::std::error::Error + ::std::marker::Send + ::std::marker::Sync,
+                        >;
+
+                        fn serialize_body(
+                            request: &#ident,
+                        ) -> ::std::result::Result<::std::vec::Vec<u8>, Self::Error> {
+                            ::std::result::Result::Ok(
+                                <#body_serializer as ::http_typed::SerializeBody<#body>>::serialize_body(
+                                    &request.body,
+                                )?,
+                            )
+                        }
+
+                        fn serialize_query(
+                            request: &#ident,
+                        ) -> ::std::result::Result<
+                            ::std::option::Option<::std::string::String>,
+                            Self::Error,
+                        > {
+                            ::std::result::Result::Ok(
+                                <::http_typed::SerdeQuery as ::http_typed::SerializeBody<#ident>>::serialize_query(
+                                    request,
+                                )?,
+                            )
+                        }
+
+                        fn content_type(
+                            request: &#ident,
+                        ) -> ::std::option::Option<::std::string::String> {
+                            <#body_serializer as ::http_typed::SerializeBody<#body>>::content_type(
+                                &request.body,
+                            )
+                        }
+                    }
+                });
+                quote!(#serializer)
+            }
+            Some(_) => body_serializer,
+            None if !query_params.is_empty() => quote!(::http_typed::SerdeQuery),
+            None => quote!(::http_typed::NoBody),
+        };
+
+        // the server only has the path parameters and either the query string
+        // or the body
+        let deserialize = self.server
+            && !header_params.iter().any(|param| param.required)
+            && (body.is_none() || (path_params.is_empty() && query_params.is_empty()));
+        let derive_deserialize = deserialize.then(|| quote!(, ::serde::Deserialize));
+        let derives = &self.derives;
+
+        let transparent = body.is_some() && serializer_impl.is_none();
+        // deserialized from the path parameters, unless the request is only
+        // its body
+        let path_attrs = match transparent {
+            true => quote!(#[serde(skip)]),
+            false => quote!(#[serde(skip_serializing)]),
+        };
+        let path_fields = path_params
+            .iter()
+            .map(|param| param.field_tokens(path_attrs.clone()));
+        let header_fields = header_params
+            .iter()
+            .map(|param| param.field_tokens(quote!(#[serde(skip)])));
+        let query_fields = query_params.iter().map(|param| {
+            let rename = rename_attr(&param.name, &param.field);
+            match param.required {
+                true => param.field_tokens(quote!(#rename)),
+                false => param.field_tokens(quote! {
+                    #rename
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                }),
+            }
+        });
+        let body_field = body.as_ref().map(|ty| match serializer_impl {
+            Some(_) => quote! {
+                #[serde(skip)]
+                pub body: #ty,
+            },
+            None => quote!(pub body: #ty,),
+        });
+        let transparent = transparent.then(|| quote!(#[serde(transparent)]));
+
+        let headers = (!header_params.is_empty()).then(|| {
+            let headers = header_params.iter().map(|param| {
+                let name = &param.name;
+                let field = &param.field;
+                if param.required {
+                    quote! {
+                        ::std::option::Option::Some((#name.to_owned(), ::std::string::ToString::to_string(&self.#field)))
+                    }
+                } else {
+                    quote! {
+                        self.#field.as_ref().map(|value| (#name.to_owned(), ::std::string::ToString::to_string(value)))
+                    }
+                }
+            });
+            quote! {
+                fn headers(&self) -> ::std::vec::Vec<(::std::string::String, ::std::string::String)> {
+                    [#(#headers),*].into_iter().flatten().collect()
+                }
+            }
+        });
+
+        let path = match args.is_empty() {
+            true => quote!(::std::borrow::ToOwned::to_owned(#format)),
+            false => quote!(::std::format!(#format, #(#args),*)),
+        };
+
+        let mut doc = vec![format!("`{description}`")];
+        for text in [&operation["summary"], &operation["description"]] {
+            if let Some(text) = text.as_str() {
+                doc.push(String::new());
+                doc.push(text.trim().to_owned());
+            }
+        }
+        let doc = doc_attr(Some(&doc.join("\n")));
+        let method = format_ident!("{}", type_name(method));
+
+        self.items.push(quote! {
+            #doc
+            #[derive(Debug, Clone, ::serde::Serialize #derive_deserialize #derives)]
+            #transparent
+            pub struct #ident {
+                #(#path_fields)*
+                #(#header_fields)*
+                #(#query_fields)*
+                #body_field
+            }
+
+            #serializer_impl
+
+            impl ::http_typed::Request for #ident {
+                type Serializer = #serializer;
+                type Deserializer = #deserializer;
+                type Response = #response;
+                type ErrorResponse = #error;
+
+                fn method(&self) -> ::http_typed::HttpMethod {
+                    ::http_typed::HttpMethod::#method
+                }
+
+                fn path(&self) -> ::std::string::String {
+                    #path
+                }
+
+                #headers
+            }
+
+            impl ::http_typed::Route for #ident {
+                const METHOD: ::http_typed::HttpMethod = ::http_typed::HttpMethod::#method;
+                const PATH: &'static str = #template;
+            }
+        });
+        self.requests.push(ident);
+        Ok(())
+    }
+
+    /// The serializer for requests with both a body and query parameters,
+    /// which is generated the first time it is used.
+    fn body_and_query(&mut self) -> Ident {
+        if let Some(ident) = &self.body_and_query {
+            return ident.clone();
+        }
+        let ident = format_ident!("{}", self.unique_name("BodyAndQuery".into()));
+        self.items.push(quote! {
+            /// Serializer for requests with both a body and query parameters.
+            /// The `body` field is sent in the body, and the other fields in
+            /// the query string.
+            pub struct #ident;
+        });
+        self.body_and_query = Some(ident.clone());
+        ident
+    }
+
+    /// Parameters of the path item, overridden by those of the operation.
+    fn params(&self, path_item: &Value, operation: &Value) -> Vec<Param> {
+        let mut params: Vec<Param> = vec![];
+        for param in [&path_item["parameters"], &operation["parameters"]]
+            .into_iter()
+            .filter_map(Value::as_array)
+            .flatten()
+        {
+            let param = self.resolve(param);
+            let (Some(name), Some(location)) = (param["name"].as_str(), param["in"].as_str())
+            else {
+                continue;
+            };
+            let required = location == "path" || param["required"].as_bool() == Some(true);
+            let param = Param {
+                name: name.to_owned(),
+                location: location.to_owned(),
+                field: field_ident(name),
+                ty: self.schema_type(&param["schema"]),
+                required,
+                doc: param["description"].as_str().map(ToOwned::to_owned),
+            };
+            params.retain(|p| p.name != param.name || p.location != param.location);
+            params.push(param);
+        }
+        params
+    }
+
+    /// The type and deserializer of the first successful response.
+    fn response_type(&mut self, name: &str, operation: &Value) -> (TokenStream, TokenStream) {
+        let responses = operation["responses"].as_object();
+        let success = responses
+            .into_iter()
+            .flatten()
+            .filter(|(status, _)| status.starts_with('2'))
+            .min_by_key(|(status, _)| status.as_str());
+        let Some((_, response)) = success else {
+            return (quote!(()), quote!(::http_typed::NoBody));
+        };
+        let content = self.resolve(response)["content"].as_object();
+        let mut content = content.into_iter().flatten().collect::<Vec<_>>();
+        content.sort_by_key(|(media_type, _)| !media_type.contains("json"));
+        match content.first() {
+            None => (quote!(()), quote!(::http_typed::NoBody)),
+            Some((media_type, media)) if media_type.contains("json") => {
+                let schema = &media["schema"];
+                let ty = self.body_type(format!("{name}Response"), schema);
+                (ty, quote!(::http_typed::SerdeJson))
+            }
+            Some((media_type, _)) if media_type.starts_with("text/") => (
+                quote!(::std::string::String),
+                quote!(::http_typed::PlainText),
+            ),
+            Some(_) => (quote!(::std::vec::Vec<u8>), quote!(::http_typed::RawBytes)),
+        }
+    }
+
+    /// The type of the json body of the default or first error response, or
+    /// String if there is none.
+    fn error_type(&mut self, name: &str, operation: &Value) -> TokenStream {
+        let responses = operation["responses"].as_object();
+        let mut errors = responses
+            .into_iter()
+            .flatten()
+            .filter(|(status, _)| {
+                *status == "default" || status.starts_with('4') || status.starts_with('5')
+            })
+            .collect::<Vec<_>>();
+        errors.sort_by_key(|(status, _)| (*status != "default", status.as_str()));
+        for (_, response) in errors {
+            let content = self.resolve(response)["content"].as_object();
+            let json = content
+                .into_iter()
+                .flatten()
+                .find(|(media_type, _)| media_type.contains("json"));
+            if let Some((_, media)) = json {
+                let schema = &media["schema"];
+                return self.body_type(format!("{name}Error"), schema);
+            }
+        }
+        quote!(::std::string::String)
+    }
+}
+
+impl Param {
+    /// The field of the request struct, with the serde attributes for where
+    /// the parameter is sent.
+    fn field_tokens(&self, attrs: TokenStream) -> TokenStream {
+        let field = &self.field;
+        let ty = if self.required {
+            self.ty.clone()
+        } else {
+            let ty = &self.ty;
+            quote!(::std::option::Option<#ty>)
+        };
+        let doc = doc_attr(self.doc.as_deref());
+        quote! {
+            #doc
+            #attrs
+            pub #field: #ty,
+        }
+    }
+}
+
+type PathFormat = (String, String, Vec<TokenStream>);
+
+/// The format string and arguments that build the path in `Request::path`,
+/// and the template for `Route::PATH` with the parameters renamed to their
+/// fields. None if a parameter in the path is not defined.
+fn path_format(path: &str, params: &[Param]) -> Result<Option<PathFormat>, Error> {
+    let unbalanced = || Error::InvalidSpec(format!("unbalanced braces in path `{path}`"));
+    let mut format = String::new();
+    let mut template = String::new();
+    let mut args = vec![];
+    let mut rest = path;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').ok_or_else(unbalanced)? + start;
+        let name = &rest[start + 1..end];
+        if rest[..start].contains('}') || name.contains('{') {
+            return Err(unbalanced());
+        }
+        let Some(param) = params.iter().find(|p| p.name == name) else {
+            return Ok(None);
+        };
+        let field = &param.field;
+        format.push_str(&rest[..start]);
+        format.push_str("{}");
+        template.push_str(&rest[..start]);
+        template.push_str(&format!("{{{}}}", unraw(field)));
+        args.push(quote! {
+            ::http_typed::encode_path_segment(&::std::string::ToString::to_string(&self.#field))
+        });
+        rest = &rest[end + 1..];
+    }
+    if rest.contains('}') {
+        return Err(unbalanced());
+    }
+    format.push_str(rest);
+    template.push_str(rest);
+    Ok(Some((format, template, args)))
+}
+
+fn doc_attr(doc: Option<&str>) -> TokenStream {
+    let lines = doc
+        .into_iter()
+        .flat_map(str::lines)
+        .map(|line| format!(" {}", line.trim_end()));
+    quote!(#(#[doc = #lines])*)
+}
+
+fn rename_attr(name: &str, field: &Ident) -> Option<TokenStream> {
+    (unraw(field) != name).then(|| quote!(#[serde(rename = #name)]))
+}
+
+fn unraw(ident: &Ident) -> String {
+    let ident = ident.to_string();
+    ident.strip_prefix("r#").unwrap_or(&ident).to_owned()
+}
+
+/// Splits an identifier from the document into lowercase words, at
+/// non-alphanumeric characters and lowercase to uppercase transitions.
+fn words(name: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut previous_lowercase = false;
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            previous_lowercase = false;
+            continue;
+        }
+        if c.is_ascii_uppercase() && previous_lowercase && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        previous_lowercase = c.is_ascii_lowercase() || c.is_ascii_digit();
+        word.push(c.to_ascii_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn type_name(name: &str) -> String {
+    let name: String = words(name)
+        .iter()
+        .map(|word| word[..1].to_ascii_uppercase() + &word[1..])
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("T{name}")
+    } else {
+        name
+    }
+}
+
+fn type_ident(name: &str) -> Ident {
+    let name = type_name(name);
+    match name.as_str() {
+        "" => format_ident!("Unnamed"),
+        "Self" => format_ident!("Self_"),
+        _ => Ident::new(&name, Span::call_site()),
+    }
+}
+
+fn field_ident(name: &str) -> Ident {
+    let name = words(name).join("_");
+    if name.is_empty() {
+        return format_ident!("field");
+    }
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        return format_ident!("_{}", name);
+    }
+    match name.as_str() {
+        "self" | "super" | "crate" => format_ident!("{}_", name),
+        _ if syn::parse_str::<Ident>(&name).is_err() => Ident::new_raw(&name, Span::call_site()),
+        _ => Ident::new(&name, Span::call_site()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn param(name: &str) -> Param {
+        Param {
+            name: name.into(),
+            location: "path".into(),
+            field: field_ident(name),
+            ty: quote!(u64),
+            required: true,
+            doc: None,
+        }
+    }
+
+    #[test]
+    fn path_parameters_are_renamed_to_fields() {
+        let (format, template, args) = path_format("/pets/{petId}/owner", &[param("petId")])
+            .unwrap()
+            .unwrap();
+        assert_eq!(format, "/pets/{}/owner");
+        assert_eq!(template, "/pets/{pet_id}/owner");
+        assert_eq!(args.len(), 1);
+    }
+
+    #[test]
+    fn undefined_path_parameters_are_skipped() {
+        assert!(path_format("/pets/{petId}", &[]).unwrap().is_none());
+    }
+
+    #[test]
+    fn unbalanced_braces_are_errors() {
+        let params = [param("id")];
+        for path in ["/a}/{id}", "/{id", "/{id}}", "/{{id}", "/{id}/}"] {
+            assert!(
+                matches!(path_format(path, &params), Err(Error::InvalidSpec(_))),
+                "{path}"
+            );
+        }
+    }
+}
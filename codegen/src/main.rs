@@ -0,0 +1,58 @@
+//! Generates http-typed Request types from an OpenAPI document.
+//!
+//! ```text
+//! http-typed-codegen <SPEC> [--group <NAME>] [--output <FILE>] [--server] [--json-schema]
+//! ```
+//!
+//! Prints the generated code to stdout unless an output file is specified.
+
+use std::process::ExitCode;
+
+use http_typed_codegen::Generator;
+
+fn main() -> ExitCode {
+    let mut spec = None;
+    let mut output = None;
+    let mut generator = Generator::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), spec.is_none()) {
+            ("--group" | "-g", _) => match args.next() {
+                Some(group) => generator = generator.group(group),
+                None => return usage(),
+            },
+            ("--output" | "-o", _) => match args.next() {
+                Some(path) => output = Some(path),
+                None => return usage(),
+            },
+            ("--server", _) => generator = generator.server(true),
+            ("--json-schema", _) => generator = generator.json_schema(true),
+            (_, true) if !arg.starts_with('-') => spec = Some(arg),
+            _ => return usage(),
+        }
+    }
+    let Some(spec) = spec else {
+        return usage();
+    };
+    let result = match output {
+        Some(output) => generator.generate_file(&spec, output),
+        None => std::fs::read_to_string(&spec)
+            .map_err(Into::into)
+            .and_then(|spec| generator.generate(&spec))
+            .map(|source| print!("{source}")),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn usage() -> ExitCode {
+    eprintln!(
+        "usage: http-typed-codegen <SPEC> [--group <NAME>] [--output <FILE>] [--server] [--json-schema]"
+    );
+    ExitCode::FAILURE
+}
@@ -0,0 +1,131 @@
+use http_typed::{DeserializeRequest, OpenApi, Request, SerializeBody};
+use http_typed_codegen::Generator;
+
+mod petstore {
+    include!("snapshots/petstore.rs");
+}
+
+use petstore::*;
+
+fn generate() -> String {
+    Generator::new()
+        .group("Petstore")
+        .server(true)
+        .json_schema(true)
+        .generate(include_str!("petstore.yaml"))
+        .unwrap()
+}
+
+/// Run with `UPDATE_SNAPSHOTS=1` to accept changes to the generated code.
+#[test]
+fn generated_code_matches_snapshot() {
+    let source = generate();
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots/petstore.rs");
+        std::fs::write(path, &source).unwrap();
+    }
+    assert_eq!(source, include_str!("snapshots/petstore.rs"));
+}
+
+#[test]
+fn unsupported_operations_are_listed() {
+    let source = generate();
+    assert!(source.contains("// - GET /owners/{ownerId}/pets: path parameters are not all defined"));
+    assert!(source
+        .contains("// - PUT /pets/{petId}/photo: request body is not json or form-urlencoded"));
+}
+
+fn pet() -> Pet {
+    Pet {
+        id: 1,
+        name: "Rex".into(),
+        status: Some(Status::Available),
+        tag: None,
+    }
+}
+
+#[test]
+fn query_parameters_are_sent_with_a_body() {
+    let request = UpdatePet {
+        pet_id: 1,
+        dry_run: Some(true),
+        body: pet(),
+    };
+    assert_eq!(request.path(), "/pets/1");
+    let query = BodyAndQuery::serialize_query(&request).unwrap();
+    assert_eq!(query.as_deref(), Some("dryRun=true"));
+    let body = BodyAndQuery::serialize_body(&request).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&body).unwrap(),
+        r#"{"id":1,"name":"Rex","status":"available","tag":null}"#
+    );
+    assert_eq!(
+        BodyAndQuery::content_type(&request).as_deref(),
+        Some("application/json")
+    );
+
+    let request = SearchPets {
+        page: 2,
+        body: SearchPetsBody {
+            query: "a b".into(),
+        },
+    };
+    let query = BodyAndQuery::serialize_query(&request).unwrap();
+    assert_eq!(query.as_deref(), Some("page=2"));
+    let body = BodyAndQuery::serialize_body(&request).unwrap();
+    assert_eq!(body, b"query=a+b");
+    assert_eq!(
+        BodyAndQuery::content_type(&request).as_deref(),
+        Some("application/x-www-form-urlencoded")
+    );
+}
+
+#[test]
+fn path_and_query_parameters() {
+    let request = ListPets {
+        limit: Some(10),
+        status: Some(Status::Sold),
+    };
+    let query = <ListPets as Request>::Serializer::serialize_query(&request).unwrap();
+    assert_eq!(query.as_deref(), Some("limit=10&status=sold"));
+
+    let request = GetPet {
+        pet_id: 7,
+        x_request_id: Some("abc".into()),
+    };
+    assert_eq!(request.path(), "/pets/7");
+    assert_eq!(request.headers(), [("X-Request-Id".into(), "abc".into())]);
+}
+
+#[test]
+fn server_rebuilds_requests() {
+    let path_params = [("pet_id".to_owned(), "7".to_owned())];
+    let request: GetPet =
+        <GetPet as Request>::Serializer::deserialize_request(b"", None, &path_params).unwrap();
+    assert_eq!(request.pet_id, 7);
+    assert_eq!(request.x_request_id, None);
+
+    let request: ListPets =
+        <ListPets as Request>::Serializer::deserialize_request(b"", Some("status=pending"), &[])
+            .unwrap();
+    assert_eq!(request.status, Some(Status::Pending));
+
+    let body = serde_json::to_vec(&pet()).unwrap();
+    let request: AddPet =
+        <AddPet as Request>::Serializer::deserialize_request(&body, None, &[]).unwrap();
+    assert_eq!(request.body, pet());
+}
+
+#[test]
+fn requests_can_be_described() {
+    let document = OpenApi::new("Petstore", "1.0.0")
+        .request::<GetPet>()
+        .request::<AddPet>()
+        .request::<ListPets>()
+        .to_json();
+    let get = &document["paths"]["/pets/{pet_id}"]["get"];
+    assert_eq!(get["parameters"][0]["name"], "pet_id");
+    assert_eq!(get["parameters"][0]["schema"]["type"], "integer");
+    let post = &document["paths"]["/pets"]["post"];
+    assert!(post["requestBody"]["content"]["application/json"].is_object());
+}
@@ -0,0 +1,418 @@
+// Generated by http-typed-codegen. Do not edit.
+//
+// Skipped operations:
+// - GET /owners/{ownerId}/pets: path parameters are not all defined
+// - PUT /pets/{petId}/photo: request body is not json or form-urlencoded
+
+/// A pet in the store
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    ::serde::Serialize,
+    ::serde::Deserialize,
+    ::schemars::JsonSchema
+)]
+pub struct Pet {
+    pub id: i64,
+    pub name: ::std::string::String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: ::std::option::Option<Status>,
+    pub tag: ::std::option::Option<::std::string::String>,
+}
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    ::serde::Serialize,
+    ::serde::Deserialize,
+    ::schemars::JsonSchema
+)]
+pub struct Problem {
+    pub detail: ::std::string::String,
+}
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    ::serde::Serialize,
+    ::serde::Deserialize,
+    ::schemars::JsonSchema
+)]
+pub enum Status {
+    #[serde(rename = "available")]
+    Available,
+    #[serde(rename = "pending")]
+    Pending,
+    #[serde(rename = "sold")]
+    Sold,
+}
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    ::serde::Serialize,
+    ::serde::Deserialize,
+    ::schemars::JsonSchema
+)]
+pub struct LoginBody {
+    pub password: ::std::string::String,
+    pub username: ::std::string::String,
+}
+/// `POST /login`
+#[derive(Debug, Clone, ::serde::Serialize, ::serde::Deserialize, ::schemars::JsonSchema)]
+#[serde(transparent)]
+pub struct Login {
+    pub body: LoginBody,
+}
+impl ::http_typed::Request for Login {
+    type Serializer = ::http_typed::FormUrlEncoded;
+    type Deserializer = ::http_typed::PlainText;
+    type Response = ::std::string::String;
+    type ErrorResponse = ::std::string::String;
+    fn method(&self) -> ::http_typed::HttpMethod {
+        ::http_typed::HttpMethod::Post
+    }
+    fn path(&self) -> ::std::string::String {
+        ::std::borrow::ToOwned::to_owned("/login")
+    }
+}
+impl ::http_typed::Route for Login {
+    const METHOD: ::http_typed::HttpMethod = ::http_typed::HttpMethod::Post;
+    const PATH: &'static str = "/login";
+}
+/// `GET /pets`
+#[derive(Debug, Clone, ::serde::Serialize, ::serde::Deserialize, ::schemars::JsonSchema)]
+pub struct ListPets {
+    /// How many pets to return
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: ::std::option::Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: ::std::option::Option<Status>,
+}
+impl ::http_typed::Request for ListPets {
+    type Serializer = ::http_typed::SerdeQuery;
+    type Deserializer = ::http_typed::SerdeJson;
+    type Response = ::std::vec::Vec<Pet>;
+    type ErrorResponse = ::std::string::String;
+    fn method(&self) -> ::http_typed::HttpMethod {
+        ::http_typed::HttpMethod::Get
+    }
+    fn path(&self) -> ::std::string::String {
+        ::std::borrow::ToOwned::to_owned("/pets")
+    }
+}
+impl ::http_typed::Route for ListPets {
+    const METHOD: ::http_typed::HttpMethod = ::http_typed::HttpMethod::Get;
+    const PATH: &'static str = "/pets";
+}
+/// `POST /pets`
+#[derive(Debug, Clone, ::serde::Serialize, ::serde::Deserialize, ::schemars::JsonSchema)]
+#[serde(transparent)]
+pub struct AddPet {
+    pub body: Pet,
+}
+impl ::http_typed::Request for AddPet {
+    type Serializer = ::http_typed::SerdeJson;
+    type Deserializer = ::http_typed::SerdeJson;
+    type Response = Pet;
+    type ErrorResponse = Problem;
+    fn method(&self) -> ::http_typed::HttpMethod {
+        ::http_typed::HttpMethod::Post
+    }
+    fn path(&self) -> ::std::string::String {
+        ::std::borrow::ToOwned::to_owned("/pets")
+    }
+}
+impl ::http_typed::Route for AddPet {
+    const METHOD: ::http_typed::HttpMethod = ::http_typed::HttpMethod::Post;
+    const PATH: &'static str = "/pets";
+}
+/// `GET /pets/{petId}`
+#[derive(Debug, Clone, ::serde::Serialize, ::serde::Deserialize, ::schemars::JsonSchema)]
+pub struct GetPet {
+    #[serde(skip_serializing)]
+    pub pet_id: i64,
+    #[serde(skip)]
+    pub x_request_id: ::std::option::Option<::std::string::String>,
+}
+impl ::http_typed::Request for GetPet {
+    type Serializer = ::http_typed::NoBody;
+    type Deserializer = ::http_typed::SerdeJson;
+    type Response = Pet;
+    type ErrorResponse = Problem;
+    fn method(&self) -> ::http_typed::HttpMethod {
+        ::http_typed::HttpMethod::Get
+    }
+    fn path(&self) -> ::std::string::String {
+        ::std::format!(
+            "/pets/{}", ::http_typed::encode_path_segment(&
+            ::std::string::ToString::to_string(& self.pet_id))
+        )
+    }
+    fn headers(
+        &self,
+    ) -> ::std::vec::Vec<(::std::string::String, ::std::string::String)> {
+        [
+            self
+                .x_request_id
+                .as_ref()
+                .map(|value| (
+                    "X-Request-Id".to_owned(),
+                    ::std::string::ToString::to_string(value),
+                )),
+        ]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
+impl ::http_typed::Route for GetPet {
+    const METHOD: ::http_typed::HttpMethod = ::http_typed::HttpMethod::Get;
+    const PATH: &'static str = "/pets/{pet_id}";
+}
+/// Serializer for requests with both a body and query parameters.
+/// The `body` field is sent in the body, and the other fields in
+/// the query string.
+pub struct BodyAndQuery;
+/// `PUT /pets/{petId}`
+#[derive(Debug, Clone, ::serde::Serialize, ::schemars::JsonSchema)]
+pub struct UpdatePet {
+    #[serde(skip_serializing)]
+    pub pet_id: i64,
+    /// Validate the pet without saving it
+    #[serde(rename = "dryRun")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dry_run: ::std::option::Option<bool>,
+    #[serde(skip)]
+    pub body: Pet,
+}
+impl ::http_typed::SerializeBody<UpdatePet> for BodyAndQuery {
+    type Error = ::std::boxed::Box<
+        dyn ::std::error::Error + ::std::marker::Send + ::std::marker::Sync,
+    >;
+    fn serialize_body(
+        request: &UpdatePet,
+    ) -> ::std::result::Result<::std::vec::Vec<u8>, Self::Error> {
+        ::std::result::Result::Ok(
+            <::http_typed::SerdeJson as ::http_typed::SerializeBody<
+                Pet,
+            >>::serialize_body(&request.body)?,
+        )
+    }
+    fn serialize_query(
+        request: &UpdatePet,
+    ) -> ::std::result::Result<
+        ::std::option::Option<::std::string::String>,
+        Self::Error,
+    > {
+        ::std::result::Result::Ok(
+            <::http_typed::SerdeQuery as ::http_typed::SerializeBody<
+                UpdatePet,
+            >>::serialize_query(request)?,
+        )
+    }
+    fn content_type(
+        request: &UpdatePet,
+    ) -> ::std::option::Option<::std::string::String> {
+        <::http_typed::SerdeJson as ::http_typed::SerializeBody<
+            Pet,
+        >>::content_type(&request.body)
+    }
+}
+impl ::http_typed::Request for UpdatePet {
+    type Serializer = BodyAndQuery;
+    type Deserializer = ::http_typed::SerdeJson;
+    type Response = Pet;
+    type ErrorResponse = ::std::string::String;
+    fn method(&self) -> ::http_typed::HttpMethod {
+        ::http_typed::HttpMethod::Put
+    }
+    fn path(&self) -> ::std::string::String {
+        ::std::format!(
+            "/pets/{}", ::http_typed::encode_path_segment(&
+            ::std::string::ToString::to_string(& self.pet_id))
+        )
+    }
+}
+impl ::http_typed::Route for UpdatePet {
+    const METHOD: ::http_typed::HttpMethod = ::http_typed::HttpMethod::Put;
+    const PATH: &'static str = "/pets/{pet_id}";
+}
+/// `DELETE /pets/{petId}`
+#[derive(Debug, Clone, ::serde::Serialize, ::schemars::JsonSchema)]
+pub struct DeletePet {
+    #[serde(skip_serializing)]
+    pub pet_id: i64,
+    #[serde(skip)]
+    pub api_key: ::std::string::String,
+}
+impl ::http_typed::Request for DeletePet {
+    type Serializer = ::http_typed::NoBody;
+    type Deserializer = ::http_typed::NoBody;
+    type Response = ();
+    type ErrorResponse = ::std::string::String;
+    fn method(&self) -> ::http_typed::HttpMethod {
+        ::http_typed::HttpMethod::Delete
+    }
+    fn path(&self) -> ::std::string::String {
+        ::std::format!(
+            "/pets/{}", ::http_typed::encode_path_segment(&
+            ::std::string::ToString::to_string(& self.pet_id))
+        )
+    }
+    fn headers(
+        &self,
+    ) -> ::std::vec::Vec<(::std::string::String, ::std::string::String)> {
+        [
+            ::std::option::Option::Some((
+                "api_key".to_owned(),
+                ::std::string::ToString::to_string(&self.api_key),
+            )),
+        ]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
+impl ::http_typed::Route for DeletePet {
+    const METHOD: ::http_typed::HttpMethod = ::http_typed::HttpMethod::Delete;
+    const PATH: &'static str = "/pets/{pet_id}";
+}
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    ::serde::Serialize,
+    ::serde::Deserialize,
+    ::schemars::JsonSchema
+)]
+pub struct RenamePetBody {
+    pub name: ::std::string::String,
+}
+/// `PATCH /pets/{petId}`
+#[derive(Debug, Clone, ::serde::Serialize, ::schemars::JsonSchema)]
+#[serde(transparent)]
+pub struct RenamePet {
+    #[serde(skip)]
+    pub pet_id: i64,
+    pub body: RenamePetBody,
+}
+impl ::http_typed::Request for RenamePet {
+    type Serializer = ::http_typed::SerdeJson;
+    type Deserializer = ::http_typed::NoBody;
+    type Response = ();
+    type ErrorResponse = ::std::string::String;
+    fn method(&self) -> ::http_typed::HttpMethod {
+        ::http_typed::HttpMethod::Patch
+    }
+    fn path(&self) -> ::std::string::String {
+        ::std::format!(
+            "/pets/{}", ::http_typed::encode_path_segment(&
+            ::std::string::ToString::to_string(& self.pet_id))
+        )
+    }
+}
+impl ::http_typed::Route for RenamePet {
+    const METHOD: ::http_typed::HttpMethod = ::http_typed::HttpMethod::Patch;
+    const PATH: &'static str = "/pets/{pet_id}";
+}
+/// `GET /pets/{petId}/name`
+#[derive(Debug, Clone, ::serde::Serialize, ::serde::Deserialize, ::schemars::JsonSchema)]
+pub struct GetPetName {
+    #[serde(skip_serializing)]
+    pub pet_id: i64,
+}
+impl ::http_typed::Request for GetPetName {
+    type Serializer = ::http_typed::NoBody;
+    type Deserializer = ::http_typed::PlainText;
+    type Response = ::std::string::String;
+    type ErrorResponse = ::std::string::String;
+    fn method(&self) -> ::http_typed::HttpMethod {
+        ::http_typed::HttpMethod::Get
+    }
+    fn path(&self) -> ::std::string::String {
+        ::std::format!(
+            "/pets/{}/name", ::http_typed::encode_path_segment(&
+            ::std::string::ToString::to_string(& self.pet_id))
+        )
+    }
+}
+impl ::http_typed::Route for GetPetName {
+    const METHOD: ::http_typed::HttpMethod = ::http_typed::HttpMethod::Get;
+    const PATH: &'static str = "/pets/{pet_id}/name";
+}
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    ::serde::Serialize,
+    ::serde::Deserialize,
+    ::schemars::JsonSchema
+)]
+pub struct SearchPetsBody {
+    pub query: ::std::string::String,
+}
+/// `POST /search`
+#[derive(Debug, Clone, ::serde::Serialize, ::schemars::JsonSchema)]
+pub struct SearchPets {
+    pub page: u32,
+    #[serde(skip)]
+    pub body: SearchPetsBody,
+}
+impl ::http_typed::SerializeBody<SearchPets> for BodyAndQuery {
+    type Error = ::std::boxed::Box<
+        dyn ::std::error::Error + ::std::marker::Send + ::std::marker::Sync,
+    >;
+    fn serialize_body(
+        request: &SearchPets,
+    ) -> ::std::result::Result<::std::vec::Vec<u8>, Self::Error> {
+        ::std::result::Result::Ok(
+            <::http_typed::FormUrlEncoded as ::http_typed::SerializeBody<
+                SearchPetsBody,
+            >>::serialize_body(&request.body)?,
+        )
+    }
+    fn serialize_query(
+        request: &SearchPets,
+    ) -> ::std::result::Result<
+        ::std::option::Option<::std::string::String>,
+        Self::Error,
+    > {
+        ::std::result::Result::Ok(
+            <::http_typed::SerdeQuery as ::http_typed::SerializeBody<
+                SearchPets,
+            >>::serialize_query(request)?,
+        )
+    }
+    fn content_type(
+        request: &SearchPets,
+    ) -> ::std::option::Option<::std::string::String> {
+        <::http_typed::FormUrlEncoded as ::http_typed::SerializeBody<
+            SearchPetsBody,
+        >>::content_type(&request.body)
+    }
+}
+impl ::http_typed::Request for SearchPets {
+    type Serializer = BodyAndQuery;
+    type Deserializer = ::http_typed::SerdeJson;
+    type Response = ::std::vec::Vec<Pet>;
+    type ErrorResponse = ::std::string::String;
+    fn method(&self) -> ::http_typed::HttpMethod {
+        ::http_typed::HttpMethod::Post
+    }
+    fn path(&self) -> ::std::string::String {
+        ::std::borrow::ToOwned::to_owned("/search")
+    }
+}
+impl ::http_typed::Route for SearchPets {
+    const METHOD: ::http_typed::HttpMethod = ::http_typed::HttpMethod::Post;
+    const PATH: &'static str = "/search";
+}
+::http_typed::request_group!(
+    pub Petstore { Login, ListPets, AddPet, GetPet, UpdatePet, DeletePet, RenamePet,
+    GetPetName, SearchPets }
+);
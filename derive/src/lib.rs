@@ -0,0 +1,197 @@
+//! Derive macro for the `Request` trait from http-typed. Use it through the
+//! `derive` feature of http-typed rather than depending on this crate directly.
+//!
+//! ```ignore
+//! #[derive(Serialize, Request)]
+//! #[request(method = "GET", path = "/users/{id}", response = User, body = NoBody)]
+//! pub struct GetUser {
+//!     id: String,
+//! }
+//! ```
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, LitStr, Path, Type};
+
+/// Implements `Request` and `Route` using the `#[request(...)]` attribute:
+/// - `method`: http method name, such as "GET" or "post" (required)
+/// - `path`: path template, where `{field}` is replaced with the percent-encoded
+///   value of that field, formatted with `Display` (required)
+/// - `response`: `Request::Response` type (required)
+/// - `error`: `Request::ErrorResponse` type, defaults to `String`
+/// - `body`: `Request::Serializer` type, defaults to `SerdeJson`
+/// - `deserializer`: `Request::Deserializer` type, defaults to `SerdeJson`
+/// - `group`: request group to add the request to, may be repeated
+#[proc_macro_derive(Request, attributes(request))]
+pub fn derive_request(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+#[derive(Default)]
+struct RequestAttr {
+    method: Option<LitStr>,
+    path: Option<LitStr>,
+    response: Option<Type>,
+    error: Option<Type>,
+    body: Option<Type>,
+    deserializer: Option<Type>,
+    groups: Vec<Path>,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let attr = parse_attr(&input)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let method = attr
+        .method
+        .ok_or_else(|| syn::Error::new(Span::call_site(), "missing `method` in #[request]"))?;
+    let method = http_method(&method)?;
+    let path = attr
+        .path
+        .ok_or_else(|| syn::Error::new(Span::call_site(), "missing `path` in #[request]"))?;
+    let path_expr = path_expr(&path, &input.data)?;
+    let response = attr
+        .response
+        .ok_or_else(|| syn::Error::new(Span::call_site(), "missing `response` in #[request]"))?;
+    let error = attr
+        .error
+        .unwrap_or_else(|| syn::parse_quote!(::std::string::String));
+    let body = attr
+        .body
+        .unwrap_or_else(|| syn::parse_quote!(::http_typed::SerdeJson));
+    let deserializer = attr
+        .deserializer
+        .unwrap_or_else(|| syn::parse_quote!(::http_typed::SerdeJson));
+    let groups = attr.groups.iter().map(|group| {
+        quote! {
+            impl #impl_generics ::http_typed::InRequestGroup<#group> for #name #ty_generics #where_clause {}
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::http_typed::Request for #name #ty_generics #where_clause {
+            type Serializer = #body;
+            type Deserializer = #deserializer;
+            type Response = #response;
+            type ErrorResponse = #error;
+
+            fn method(&self) -> ::http_typed::HttpMethod {
+                ::http_typed::HttpMethod::#method
+            }
+
+            fn path(&self) -> ::std::string::String {
+                #path_expr
+            }
+        }
+
+        impl #impl_generics ::http_typed::Route for #name #ty_generics #where_clause {
+            const METHOD: ::http_typed::HttpMethod = ::http_typed::HttpMethod::#method;
+            const PATH: &'static str = #path;
+        }
+
+        #(#groups)*
+    })
+}
+
+fn parse_attr(input: &DeriveInput) -> syn::Result<RequestAttr> {
+    let mut attr = RequestAttr::default();
+    let mut found = false;
+    for a in input.attrs.iter().filter(|a| a.path().is_ident("request")) {
+        found = true;
+        a.parse_nested_meta(|meta| {
+            if meta.path.is_ident("method") {
+                attr.method = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("path") {
+                attr.path = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("response") {
+                attr.response = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("error") {
+                attr.error = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("body") {
+                attr.body = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("deserializer") {
+                attr.deserializer = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("group") {
+                attr.groups.push(meta.value()?.parse()?);
+            } else {
+                return Err(meta.error("unsupported #[request] attribute"));
+            }
+            Ok(())
+        })?;
+    }
+    if !found {
+        return Err(syn::Error::new(
+            Span::call_site(),
+            "missing #[request(...)] attribute",
+        ));
+    }
+    Ok(attr)
+}
+
+fn http_method(method: &LitStr) -> syn::Result<Ident> {
+    let variant = match method.value().to_ascii_uppercase().as_str() {
+        "OPTIONS" => "Options",
+        "GET" => "Get",
+        "POST" => "Post",
+        "PUT" => "Put",
+        "DELETE" => "Delete",
+        "HEAD" => "Head",
+        "TRACE" => "Trace",
+        "CONNECT" => "Connect",
+        "PATCH" => "Patch",
+        _ => return Err(syn::Error::new(method.span(), "unknown http method")),
+    };
+    Ok(format_ident!("{}", variant))
+}
+
+/// Splits the template into literal parts and `{field}` parameters, and builds
+/// an expression that formats it into a String.
+fn path_expr(template: &LitStr, data: &Data) -> syn::Result<TokenStream2> {
+    let value = template.value();
+    let mut format = String::new();
+    let mut args = vec![];
+    let mut rest = value.as_str();
+    while let Some(start) = rest.find(['{', '}']) {
+        if rest[start..].starts_with('}') {
+            return Err(syn::Error::new(template.span(), "unmatched `}` in path"));
+        }
+        format.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| syn::Error::new(template.span(), "unmatched `{` in path"))?;
+        let field = &rest[start + 1..start + end];
+        if !has_field(data, field) {
+            return Err(syn::Error::new(
+                template.span(),
+                format!("path parameter `{field}` is not a field of this struct"),
+            ));
+        }
+        let field = format_ident!("{}", field);
+        format.push_str("{}");
+        args.push(quote! {
+            ::http_typed::encode_path_segment(&::std::string::ToString::to_string(&self.#field))
+        });
+        rest = &rest[start + end + 1..];
+    }
+    format.push_str(rest);
+    Ok(quote! { ::std::format!(#format, #(#args),*) })
+}
+
+fn has_field(data: &Data, name: &str) -> bool {
+    match data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields
+                .named
+                .iter()
+                .any(|field| field.ident.as_ref().is_some_and(|ident| ident == name)),
+            _ => false,
+        },
+        _ => false,
+    }
+}
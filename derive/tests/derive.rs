@@ -0,0 +1,147 @@
+use std::marker::PhantomData;
+
+use http_typed::{
+    request_group, HttpMethod, InRequestGroup, NoBody, OpenApi, PlainText, Request, Route,
+    SerdeJson, SerdeQuery,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, JsonSchema)]
+pub struct User {
+    pub id: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+pub struct Problem {
+    pub detail: String,
+}
+
+#[derive(Serialize, JsonSchema, Request)]
+#[request(method = "GET", path = "/users/{id}", response = User, body = NoBody)]
+pub struct GetUser {
+    id: String,
+}
+
+#[derive(Serialize, JsonSchema, Request)]
+#[request(
+    method = "put",
+    path = "/orgs/{org}/users/{id}/name",
+    response = String,
+    error = Problem,
+    deserializer = PlainText
+)]
+pub struct RenameUser {
+    org: u32,
+    id: String,
+    name: String,
+}
+
+#[derive(Serialize, Request)]
+#[request(method = "GET", path = "/items", response = Vec<T>, body = SerdeQuery)]
+pub struct ListItems<T: for<'de> Deserialize<'de>> {
+    limit: u32,
+    #[serde(skip)]
+    item: PhantomData<T>,
+}
+
+request_group!(pub Listed { GetUser, RenameUser });
+request_group!(pub Derived {});
+
+#[derive(Serialize, Request)]
+#[request(method = "DELETE", path = "/users/{id}", response = (), group = Derived, group = Listed)]
+pub struct DeleteUser {
+    id: String,
+}
+
+fn same_type<T>(_: PhantomData<T>, _: PhantomData<T>) {}
+
+fn in_group<Req: InRequestGroup<Group>, Group>() {}
+
+#[test]
+fn path_values_are_encoded() {
+    let request = GetUser { id: "a/b c".into() };
+    assert_eq!(request.path(), "/users/a%2Fb%20c");
+    let request = RenameUser {
+        org: 7,
+        id: "x?y".into(),
+        name: "new".into(),
+    };
+    assert_eq!(request.path(), "/orgs/7/users/x%3Fy/name");
+}
+
+#[test]
+fn method_and_route() {
+    assert!(matches!(
+        GetUser { id: "1".into() }.method(),
+        HttpMethod::Get
+    ));
+    assert!(matches!(GetUser::METHOD, HttpMethod::Get));
+    assert_eq!(GetUser::PATH, "/users/{id}");
+    assert!(matches!(RenameUser::METHOD, HttpMethod::Put));
+    assert_eq!(RenameUser::PATH, "/orgs/{org}/users/{id}/name");
+}
+
+#[test]
+fn associated_types() {
+    same_type(
+        PhantomData::<<GetUser as Request>::Serializer>,
+        PhantomData::<NoBody>,
+    );
+    same_type(
+        PhantomData::<<GetUser as Request>::Deserializer>,
+        PhantomData::<SerdeJson>,
+    );
+    same_type(
+        PhantomData::<<GetUser as Request>::ErrorResponse>,
+        PhantomData::<String>,
+    );
+    same_type(
+        PhantomData::<<RenameUser as Request>::Serializer>,
+        PhantomData::<SerdeJson>,
+    );
+    same_type(
+        PhantomData::<<RenameUser as Request>::Deserializer>,
+        PhantomData::<PlainText>,
+    );
+    same_type(
+        PhantomData::<<RenameUser as Request>::ErrorResponse>,
+        PhantomData::<Problem>,
+    );
+}
+
+#[test]
+fn generic_request() {
+    let request = ListItems::<u8> {
+        limit: 10,
+        item: PhantomData,
+    };
+    assert_eq!(request.path(), "/items");
+    same_type(
+        PhantomData::<<ListItems<u8> as Request>::Response>,
+        PhantomData::<Vec<u8>>,
+    );
+}
+
+#[test]
+fn groups() {
+    in_group::<GetUser, Listed>();
+    in_group::<DeleteUser, Listed>();
+    in_group::<DeleteUser, Derived>();
+}
+
+#[test]
+fn openapi_describes_listed_requests() {
+    let document = OpenApi::new("Test", "1.0.0").group::<Listed>().to_json();
+    let paths = document["paths"].as_object().unwrap();
+    assert!(paths.contains_key("/users/{id}"));
+    assert!(paths.contains_key("/orgs/{org}/users/{id}/name"));
+    // added with `group =`, so it is not listed
+    assert!(paths["/users/{id}"].get("delete").is_none());
+}
+
+#[test]
+fn ui() {
+    let tests = trybuild::TestCases::new();
+    tests.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,6 @@
+use http_typed::Request;
+
+#[derive(serde::Serialize, Request)]
+struct ListUsers;
+
+fn main() {}
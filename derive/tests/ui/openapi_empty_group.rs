@@ -0,0 +1,11 @@
+use http_typed::{request_group, OpenApi, Request};
+
+request_group!(pub MyApi {});
+
+#[derive(serde::Serialize, schemars::JsonSchema, Request)]
+#[request(method = "GET", path = "/users", response = (), group = MyApi)]
+struct ListUsers;
+
+fn main() {
+    OpenApi::new("My API", "1.0.0").group::<MyApi>();
+}
@@ -0,0 +1,7 @@
+use http_typed::Request;
+
+#[derive(serde::Serialize, Request)]
+#[request(method = "FETCH", path = "/users", response = ())]
+struct ListUsers;
+
+fn main() {}
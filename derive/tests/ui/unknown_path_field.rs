@@ -0,0 +1,9 @@
+use http_typed::Request;
+
+#[derive(serde::Serialize, Request)]
+#[request(method = "GET", path = "/users/{user_id}", response = ())]
+struct GetUser {
+    id: String,
+}
+
+fn main() {}
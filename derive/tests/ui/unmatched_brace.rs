@@ -0,0 +1,15 @@
+use http_typed::Request;
+
+#[derive(serde::Serialize, Request)]
+#[request(method = "GET", path = "/users/{id", response = ())]
+struct GetUser {
+    id: String,
+}
+
+#[derive(serde::Serialize, Request)]
+#[request(method = "GET", path = "/users/}{id}", response = ())]
+struct GetOtherUser {
+    id: String,
+}
+
+fn main() {}
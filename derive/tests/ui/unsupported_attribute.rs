@@ -0,0 +1,7 @@
+use http_typed::Request;
+
+#[derive(serde::Serialize, Request)]
+#[request(method = "GET", path = "/users", response = (), timeout = 5)]
+struct ListUsers;
+
+fn main() {}
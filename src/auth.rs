@@ -0,0 +1,343 @@
+use std::{future::Future, sync::Arc};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::{
+    header::{HeaderName, AUTHORIZATION},
+    HeaderValue, StatusCode,
+};
+
+use crate::{
+    client::{append_query, send_error, Url},
+    middleware::{BoxError, HttpRequest, HttpResponse, SendError},
+    Error,
+};
+
+/// Credentials that a Client adds to every request it sends, unless the
+/// request opts out with `Request::authenticate`.
+///
+/// ```ignore
+/// let client = Client::<MyApi>::new("http://example.com".into())?
+///     .auth(Auth::bearer("my-token"));
+/// ```
+///
+/// If a request already has the header that the credentials would be sent in,
+/// from `Request::headers`, it is left unchanged.
+#[derive(Clone)]
+pub struct Auth(Credentials);
+
+#[derive(Clone)]
+enum Credentials {
+    Bearer(String),
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+    ApiKeyHeader {
+        name: String,
+        value: String,
+    },
+    ApiKeyQuery {
+        name: String,
+        value: String,
+    },
+    Provider(Arc<dyn TokenProvider>),
+}
+
+/// Provides bearer tokens that can change over time, such as tokens that
+/// expire and must be fetched again. The token is requested for every request
+/// sent with `Auth::provider`, so implementations should cache it.
+///
+/// If a request is rejected with status 401, the provider is asked to
+/// invalidate the token, and the request is sent once more with a new token if
+/// `invalidate` returns true.
+///
+/// ```ignore
+/// struct FromFile(PathBuf);
+///
+/// #[async_trait::async_trait]
+/// impl TokenProvider for FromFile {
+///     async fn token(&self) -> Result<String, BoxError> {
+///         Ok(std::fs::read_to_string(&self.0)?.trim().to_owned())
+///     }
+/// }
+/// ```
+#[async_trait::async_trait]
+pub trait TokenProvider: Send + Sync + 'static {
+    async fn token(&self) -> Result<String, BoxError>;
+
+    /// Called when a request with the token was rejected with status 401.
+    /// Returns whether the token was discarded, so that `token` will return a
+    /// different one. Defaults to false.
+    async fn invalidate(&self, token: &str) -> bool {
+        let _ = token;
+        false
+    }
+}
+
+impl Auth {
+    /// Send the token in the header `Authorization: Bearer {token}`.
+    pub fn bearer(token: impl Into<String>) -> Self {
+        Self(Credentials::Bearer(token.into()))
+    }
+
+    /// Send the username and password in the header
+    /// `Authorization: Basic {base64}`.
+    pub fn basic(username: impl Into<String>, password: Option<impl Into<String>>) -> Self {
+        Self(Credentials::Basic {
+            username: username.into(),
+            password: password.map(Into::into),
+        })
+    }
+
+    /// Send an api key as the value of the named header.
+    pub fn api_key_header(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self(Credentials::ApiKeyHeader {
+            name: name.into(),
+            value: value.into(),
+        })
+    }
+
+    /// Send an api key as the named parameter in the query string.
+    pub fn api_key_query(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self(Credentials::ApiKeyQuery {
+            name: name.into(),
+            value: value.into(),
+        })
+    }
+
+    /// Send a bearer token from the provider, which is asked for a token each
+    /// time a request is sent.
+    pub fn provider(provider: impl TokenProvider) -> Self {
+        Self(Credentials::Provider(Arc::new(provider)))
+    }
+
+    /// Add the credentials to the request, and send it. A request that is
+    /// rejected with a token from a provider may be sent twice.
+    pub(crate) async fn send<Ser, ErrRes, De, Fut>(
+        &self,
+        request: HttpRequest,
+        send: impl Fn(HttpRequest) -> Fut,
+    ) -> Result<HttpResponse, Error<Ser, ErrRes, De>>
+    where
+        Fut: Future<Output = Result<HttpResponse, SendError>>,
+    {
+        let Credentials::Provider(provider) = &self.0 else {
+            let mut request = request;
+            self.apply(&mut request).await.map_err(Error::AuthError)?;
+            return send(request).await.map_err(send_error);
+        };
+        let mut authenticated = request.clone();
+        let token = self
+            .apply(&mut authenticated)
+            .await
+            .map_err(Error::AuthError)?;
+        let response = send(authenticated).await.map_err(send_error)?;
+        match token {
+            Some(token)
+                if response.status == StatusCode::UNAUTHORIZED
+                    && provider.invalidate(&token).await =>
+            {
+                let mut request = request;
+                self.apply(&mut request).await.map_err(Error::AuthError)?;
+                send(request).await.map_err(send_error)
+            }
+            _ => Ok(response),
+        }
+    }
+
+    /// Add the credentials to the request. Returns the token if one from a
+    /// provider was added.
+    async fn apply(&self, request: &mut HttpRequest) -> Result<Option<String>, BoxError> {
+        let Credentials::Provider(provider) = &self.0 else {
+            return self.apply_static(request).map(|()| None);
+        };
+        if request.headers.contains_key(AUTHORIZATION) {
+            return Ok(None);
+        }
+        let token = provider.token().await?;
+        let mut value = HeaderValue::from_str(&format!("Bearer {token}"))?;
+        value.set_sensitive(true);
+        request.headers.insert(AUTHORIZATION, value);
+        Ok(Some(token))
+    }
+
+    /// Add credentials that do not come from a provider to the request. Fails
+    /// for a provider, which can only be asked for a token asynchronously.
+    pub(crate) fn apply_static(&self, request: &mut HttpRequest) -> Result<(), BoxError> {
+        let (name, value) = match &self.0 {
+            Credentials::Bearer(token) => (AUTHORIZATION, format!("Bearer {token}")),
+            Credentials::Basic { username, password } => {
+                let credentials = format!("{username}:{}", password.as_deref().unwrap_or(""));
+                (
+                    AUTHORIZATION,
+                    format!("Basic {}", STANDARD.encode(credentials)),
+                )
+            }
+            Credentials::ApiKeyHeader { name, value } => {
+                (HeaderName::from_bytes(name.as_bytes())?, value.clone())
+            }
+            Credentials::ApiKeyQuery { name, value } => {
+                let query = serde_urlencoded::to_string([(name, value)])?;
+                let mut url = Url::parse(&request.url)?;
+                append_query(&mut url, &query);
+                request.url = url.into();
+                return Ok(());
+            }
+            Credentials::Provider(_) => {
+                return Err("a TokenProvider can only be used by the async Client".into())
+            }
+        };
+        let mut value = HeaderValue::from_str(&value)?;
+        value.set_sensitive(true);
+        request.headers.entry(name).or_insert(value);
+        Ok(())
+    }
+}
+
+/// Does not show any secrets.
+impl std::fmt::Debug for Auth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Credentials::Bearer(_) => f.write_str("Auth::Bearer"),
+            Credentials::Basic { username, .. } => f
+                .debug_struct("Auth::Basic")
+                .field("username", username)
+                .finish_non_exhaustive(),
+            Credentials::ApiKeyHeader { name, .. } => f
+                .debug_struct("Auth::ApiKeyHeader")
+                .field("name", name)
+                .finish_non_exhaustive(),
+            Credentials::ApiKeyQuery { name, .. } => f
+                .debug_struct("Auth::ApiKeyQuery")
+                .field("name", name)
+                .finish_non_exhaustive(),
+            Credentials::Provider(_) => f.write_str("Auth::Provider"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::{
+        testing::{response, Ping, Stub},
+        All, Client, RequestError,
+    };
+
+    async fn send(
+        auth: Auth,
+        stub: Stub,
+    ) -> (Result<String, RequestError<Ping>>, Vec<HttpRequest>) {
+        let stub = Arc::new(stub);
+        let client = Client::<All, _>::with_inner("http://example.com".into(), stub.clone())
+            .unwrap()
+            .auth(auth);
+        let result = client.send(Ping).await;
+        let requests = stub.requests.lock().unwrap().clone();
+        (result, requests)
+    }
+
+    fn header<'a>(request: &'a HttpRequest, name: &str) -> Option<&'a str> {
+        request
+            .headers
+            .get(name)
+            .map(|value| value.to_str().unwrap())
+    }
+
+    #[tokio::test]
+    async fn static_credentials() {
+        let (_, requests) = send(Auth::bearer("abc"), Stub::ok()).await;
+        assert_eq!(header(&requests[0], "authorization"), Some("Bearer abc"));
+        assert!(requests[0].headers["authorization"].is_sensitive());
+
+        let (_, requests) = send(Auth::basic("user", Some("pass")), Stub::ok()).await;
+        assert_eq!(
+            header(&requests[0], "authorization"),
+            Some("Basic dXNlcjpwYXNz")
+        );
+        let (_, requests) = send(Auth::basic("user", None::<String>), Stub::ok()).await;
+        assert_eq!(
+            header(&requests[0], "authorization"),
+            Some("Basic dXNlcjo=")
+        );
+
+        let (_, requests) = send(Auth::api_key_header("X-Api-Key", "k"), Stub::ok()).await;
+        assert_eq!(header(&requests[0], "x-api-key"), Some("k"));
+        assert_eq!(header(&requests[0], "authorization"), None);
+
+        let (_, requests) = send(Auth::api_key_query("key", "a&b c"), Stub::ok()).await;
+        assert_eq!(requests[0].url, "http://example.com/ping?key=a%26b+c");
+        assert_eq!(header(&requests[0], "authorization"), None);
+    }
+
+    #[tokio::test]
+    async fn invalid_header_name_is_an_auth_error() {
+        let (result, requests) = send(Auth::api_key_header("bad name", "k"), Stub::ok()).await;
+        assert!(matches!(result, Err(Error::AuthError(_))));
+        assert!(requests.is_empty());
+    }
+
+    /// Returns `t0`, then `t1` once `t0` is invalidated, and so on.
+    struct Rotating {
+        generation: Mutex<u32>,
+        invalidates: bool,
+    }
+
+    impl Rotating {
+        fn new(invalidates: bool) -> Self {
+            Self {
+                generation: Mutex::new(0),
+                invalidates,
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl TokenProvider for Rotating {
+        async fn token(&self) -> Result<String, BoxError> {
+            Ok(format!("t{}", self.generation.lock().unwrap()))
+        }
+
+        async fn invalidate(&self, token: &str) -> bool {
+            let mut generation = self.generation.lock().unwrap();
+            if !self.invalidates || token != format!("t{generation}") {
+                return false;
+            }
+            *generation += 1;
+            true
+        }
+    }
+
+    /// Rejects every token except `accepted`.
+    fn accepting(accepted: &'static str) -> Stub {
+        Stub::new(move |request| match header(request, "authorization") {
+            Some(value) if value == format!("Bearer {accepted}") => Ok(response(200, b"pong")),
+            _ => Ok(response(401, b"expired")),
+        })
+    }
+
+    #[tokio::test]
+    async fn rejected_token_is_refreshed_once() {
+        let (result, requests) = send(Auth::provider(Rotating::new(true)), accepting("t1")).await;
+        assert_eq!(result.unwrap(), "pong");
+        let tokens: Vec<_> = requests
+            .iter()
+            .map(|request| header(request, "authorization").unwrap())
+            .collect();
+        assert_eq!(tokens, ["Bearer t0", "Bearer t1"]);
+
+        // the new token is rejected too, so the 401 is returned
+        let (result, requests) = send(Auth::provider(Rotating::new(true)), accepting("t2")).await;
+        assert!(matches!(result, Err(Error::Api { status: 401, .. })));
+        assert_eq!(requests.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn rejected_token_is_not_resent_unless_invalidated() {
+        let (result, requests) = send(Auth::provider(Rotating::new(false)), accepting("t1")).await;
+        assert!(matches!(result, Err(Error::Api { status: 401, .. })));
+        assert_eq!(requests.len(), 1);
+    }
+}
@@ -0,0 +1,371 @@
+//! A synchronous Client for code that does not run in an async runtime, such
+//! as command line tools and build scripts. It has the same typed API as the
+//! async Client, and sends requests with reqwest's blocking client.
+//!
+//! ```ignore
+//! let client = blocking::Client::<MyApi>::new("http://example.com".into())?;
+//! let user = client.send(GetUser { id: "123".into() })?;
+//! ```
+//!
+//! Like `reqwest::blocking`, this must not be used from within an async
+//! runtime.
+//!
+//! `Auth` and `Signer` work the same as in the async Client, except for
+//! `Auth::provider`, which fails every request with `Error::AuthError`
+//! because a `TokenProvider` is async. These are only supported by the async
+//! Client:
+//! - middleware, including `Cassette`
+//! - retries with a `RetryPolicy`
+//! - token providers, including `ClientCredentials`
+//! - transports other than reqwest
+//! - `builder()`. Configure a `reqwest::blocking::Client` instead, and pass it
+//!   to `with_inner`.
+
+use std::{any::type_name, marker::PhantomData, sync::Arc, time::Instant};
+
+use crate::{
+    client::{deserialize_response, http_request, join_url, parse_base_url},
+    middleware::{HttpRequest, HttpResponse, Signer},
+    All, Auth, DeserializeBody, Error, HttpMethod, InRequestGroup, Request, RequestError, Response,
+    SerdeJson, SimpleBody, Url, UrlError,
+};
+
+/// A blocking version of `http_typed::Client`. See the module documentation.
+pub struct Client<RequestGroup = All> {
+    base_url: Option<Url>,
+    inner: reqwest::blocking::Client,
+    auth: Option<Auth>,
+    signer: Option<Arc<dyn Signer>>,
+    _p: PhantomData<RequestGroup>,
+}
+
+/// Explicitly implemented to avoid requirement RequestGroup: Debug
+impl<RequestGroup> std::fmt::Debug for Client<RequestGroup> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(type_name::<Self>())
+            .field("base_url", &self.base_url.as_ref().map(Url::as_str))
+            .field("inner", &self.inner)
+            .field("auth", &self.auth)
+            .field("signer", &self.signer.is_some())
+            .finish()
+    }
+}
+
+/// Explicitly implemented to avoid requirement RequestGroup: Default
+impl<RequestGroup> Default for Client<RequestGroup> {
+    fn default() -> Self {
+        Self {
+            base_url: Default::default(),
+            inner: Default::default(),
+            auth: None,
+            signer: None,
+            _p: PhantomData,
+        }
+    }
+}
+
+/// Explicitly implemented to avoid requirement RequestGroup: Clone
+impl<RequestGroup> Clone for Client<RequestGroup> {
+    fn clone(&self) -> Self {
+        Self {
+            base_url: self.base_url.clone(),
+            inner: self.inner.clone(),
+            auth: self.auth.clone(),
+            signer: self.signer.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<RequestGroup> Client<RequestGroup> {
+    /// Returns an error if base_url is not a valid absolute url. See
+    /// `http_typed::Client::new`.
+    pub fn new(base_url: String) -> Result<Self, UrlError> {
+        Self::with_inner(base_url, reqwest::blocking::Client::new())
+    }
+
+    /// Use a pre-configured reqwest client to send requests.
+    pub fn with_inner(
+        base_url: String,
+        inner: reqwest::blocking::Client,
+    ) -> Result<Self, UrlError> {
+        Ok(Self {
+            base_url: parse_base_url(&base_url)?,
+            inner,
+            auth: None,
+            signer: None,
+            _p: PhantomData,
+        })
+    }
+
+    /// Add credentials to every request sent by this client, unless the
+    /// request opts out with `Request::authenticate`. See
+    /// `http_typed::Client::auth`.
+    pub fn auth(mut self, auth: Auth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Sign every request sent by this client, after credentials are added.
+    /// See `http_typed::Client::signer`.
+    pub fn signer(mut self, signer: impl Signer) -> Self {
+        self.signer = Some(Arc::new(signer));
+        self
+    }
+
+    /// The url that a request with the given path is sent to. The parts are
+    /// joined, and `.` and `..` segments rejected, with the rules described in
+    /// `http_typed::Client::url`.
+    pub fn url(&self, url_infix: &str, path: &str) -> Result<Url, UrlError> {
+        join_url(self.base_url.as_ref(), url_infix, path)
+    }
+
+    /// Send the provided request to the host at this client's base_url, using
+    /// the Request implementation to determine the remaining url path and
+    /// request data.
+    ///
+    /// The url used for the request is {self.base_url}{request.path()}, joined
+    /// as described in `http_typed::Client::url`.
+    pub fn send<Req>(&self, request: Req) -> Result<Req::Response, RequestError<Req>>
+    where
+        Req: Request + InRequestGroup<RequestGroup>,
+        Req::ErrorResponse: for<'a> serde::Deserialize<'a>,
+    {
+        self.send_simple::<_, _, _, Req::Deserializer>(
+            self.url("", &request.path())?,
+            request.method(),
+            request.authenticate(),
+            request,
+        )
+        .map(|response| response.value)
+    }
+
+    /// Same as `send`, but also returns the status code, headers, and other
+    /// metadata about the http response.
+    pub fn send_with_meta<Req>(
+        &self,
+        request: Req,
+    ) -> Result<Response<Req::Response>, RequestError<Req>>
+    where
+        Req: Request + InRequestGroup<RequestGroup>,
+        Req::ErrorResponse: for<'a> serde::Deserialize<'a>,
+    {
+        self.send_simple::<_, _, _, Req::Deserializer>(
+            self.url("", &request.path())?,
+            request.method(),
+            request.authenticate(),
+            request,
+        )
+    }
+
+    /// Send the provided request to the host at this client's base_url plus
+    /// url_infix, using the Request implementation to determine the remaining
+    /// url path and request data.
+    ///
+    /// The url used for the request is
+    /// {self.base_url}{url_infix}{request.path()}, joined as described in
+    /// `http_typed::Client::url`.
+    pub fn send_to<Req>(
+        &self,
+        url_infix: &str,
+        request: Req,
+    ) -> Result<Req::Response, RequestError<Req>>
+    where
+        Req: Request + InRequestGroup<RequestGroup>,
+        Req::ErrorResponse: for<'a> serde::Deserialize<'a>,
+    {
+        self.send_simple::<_, _, _, Req::Deserializer>(
+            self.url(url_infix, &request.path())?,
+            request.method(),
+            request.authenticate(),
+            request,
+        )
+        .map(|response| response.value)
+    }
+
+    /// Same as `send_to`, but also returns the status code, headers, and other
+    /// metadata about the http response.
+    pub fn send_to_with_meta<Req>(
+        &self,
+        url_infix: &str,
+        request: Req,
+    ) -> Result<Response<Req::Response>, RequestError<Req>>
+    where
+        Req: Request + InRequestGroup<RequestGroup>,
+        Req::ErrorResponse: for<'a> serde::Deserialize<'a>,
+    {
+        self.send_simple::<_, _, _, Req::Deserializer>(
+            self.url(url_infix, &request.path())?,
+            request.method(),
+            request.authenticate(),
+            request,
+        )
+    }
+
+    /// Send the provided request to the specified path using the specified method,
+    /// and deserialize the response into the specified response type.
+    ///
+    /// The url used for this request is {self.base_url}{path}, joined as
+    /// described in `http_typed::Client::url`.
+    pub fn send_custom<Req, Res>(
+        &self,
+        path: &str,
+        method: HttpMethod,
+        request: Req,
+    ) -> Result<Res, Error<Req::Error>>
+    where
+        Req: SimpleBody,
+        Res: for<'a> serde::Deserialize<'a>,
+    {
+        self.send_simple::<_, _, _, SerdeJson>(self.url("", path)?, method, true, request)
+            .map(|response| response.value)
+    }
+
+    fn send_simple<Req, Res, ErrRes, De>(
+        &self,
+        url: Url,
+        method: HttpMethod,
+        authenticate: bool,
+        request: Req,
+    ) -> Result<Response<Res>, Error<Req::Error, ErrRes, De::Error>>
+    where
+        Req: SimpleBody,
+        ErrRes: for<'a> serde::Deserialize<'a>,
+        De: DeserializeBody<Res>,
+    {
+        let mut request = http_request(url, method, &request, De::accept())?;
+        if let Some(auth) = self.auth.as_ref().filter(|_| authenticate) {
+            auth.apply_static(&mut request).map_err(Error::AuthError)?;
+        }
+        if let Some(signer) = &self.signer {
+            signer.sign(&mut request).map_err(Error::AuthError)?;
+        }
+        let start = Instant::now();
+        let response = send_http(&self.inner, request)?;
+        let elapsed = start.elapsed();
+        let value = deserialize_response::<_, _, _, De>(&response)?;
+        Ok(Response {
+            value,
+            status: response.status,
+            headers: response.headers,
+            url: response.url,
+            elapsed,
+        })
+    }
+}
+
+/// Convenience function to create a client and send a request using minimal
+/// boilerplate. Creating a client is expensive, so you should not use this
+/// function if you plan on sending multiple requests.
+///
+/// The url used for the request is {base_url}{request.path()}, joined as
+/// described in `http_typed::Client::url`.
+pub fn send<Req>(base_url: &str, request: Req) -> Result<Req::Response, RequestError<Req>>
+where
+    Req: Request,
+    Req::ErrorResponse: for<'a> serde::Deserialize<'a>,
+{
+    Client::<All>::new(base_url.to_owned())?.send(request)
+}
+
+/// Convenience function to create a client and send a request using minimal
+/// boilerplate. Creating a client is expensive, so you should not use this
+/// function if you plan on sending multiple requests.
+///
+/// Send the provided request to the specified url using the specified method,
+/// and deserialize the response into the specified response type.
+pub fn send_custom<Req, Res>(
+    url: &str,
+    method: HttpMethod,
+    request: Req,
+) -> Result<Res, Error<Req::Error>>
+where
+    Req: SimpleBody,
+    Res: for<'a> serde::Deserialize<'a>,
+{
+    Client::<All>::default().send_custom(url, method, request)
+}
+
+fn send_http(
+    client: &reqwest::blocking::Client,
+    request: HttpRequest,
+) -> Result<HttpResponse, reqwest::Error> {
+    let response = client
+        .request(request.method.into(), &request.url)
+        .headers(request.headers)
+        .body(request.body)
+        .send()?;
+    let status = response.status();
+    let headers = response.headers().clone();
+    let url = response.url().to_string();
+    let body = response.bytes()?.to_vec();
+    Ok(HttpResponse {
+        status,
+        headers,
+        url,
+        body,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        middleware::BoxError,
+        testing::{serve_once, Ping},
+        TokenProvider,
+    };
+
+    struct Stamp;
+
+    impl Signer for Stamp {
+        fn sign(&self, request: &mut HttpRequest) -> Result<(), BoxError> {
+            let authorization = request.headers["authorization"].clone();
+            request.headers.insert("x-signed", authorization);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn auth_and_signer_are_applied() {
+        let (base_url, server) = serve_once();
+        let client = Client::<All>::new(base_url)
+            .unwrap()
+            .auth(Auth::bearer("t0ken"))
+            .signer(Stamp);
+        assert_eq!(client.send(Ping).unwrap(), "pong");
+        let head = server.join().unwrap();
+        assert!(head.contains("authorization: bearer t0ken\r\n"), "{head}");
+        assert!(head.contains("x-signed: bearer t0ken\r\n"), "{head}");
+    }
+
+    #[test]
+    fn api_key_in_query() {
+        let (base_url, server) = serve_once();
+        let client = Client::<All>::new(base_url)
+            .unwrap()
+            .auth(Auth::api_key_query("key", "k 1"));
+        assert_eq!(client.send(Ping).unwrap(), "pong");
+        assert!(server
+            .join()
+            .unwrap()
+            .starts_with("get /ping?key=k+1 http/1.1"));
+    }
+
+    #[test]
+    fn token_provider_is_rejected() {
+        struct Never;
+
+        #[async_trait::async_trait]
+        impl TokenProvider for Never {
+            async fn token(&self) -> Result<String, BoxError> {
+                unreachable!()
+            }
+        }
+
+        let client = Client::<All>::new("http://127.0.0.1:9".into())
+            .unwrap()
+            .auth(Auth::provider(Never));
+        assert!(matches!(client.send(Ping), Err(Error::AuthError(_))));
+    }
+}
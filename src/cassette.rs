@@ -0,0 +1,676 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::middleware::{BoxError, HttpRequest, HttpResponse, Middleware, Next};
+
+/// A middleware that records the http requests sent by a Client along with
+/// their responses to a json file, and later replays them without sending
+/// anything over the network. This lets integration tests run offline.
+///
+/// ```ignore
+/// // record once against a real server
+/// let cassette = Cassette::record("tests/cassettes/users.json")
+///     .redact_header("authorization")
+///     .redact_query("api_key");
+/// let client = Client::<MyApi>::new("http://example.com".into())?.with(cassette.clone());
+/// run_tests(&client).await;
+/// cassette.save()?;
+///
+/// // replay in CI
+/// let cassette = Cassette::replay("tests/cassettes/users.json")?;
+/// let client = Client::<MyApi>::new("http://example.com".into())?.with(cassette);
+/// ```
+///
+/// Recorded interactions are kept in memory, and only written to the file by
+/// `save`, so recording does not block the async runtime with file I/O. Clones
+/// share the same interactions, so keep a clone to save after the client has
+/// sent its requests.
+///
+/// A request is replayed with the first recorded interaction that matches it
+/// and has not been replayed yet. If they have all been replayed, the last
+/// matching interaction is replayed again. Requests always match on method and
+/// path, but not on the scheme or host of the url, so a recording can be
+/// replayed by a client with a different base url. By default they also match
+/// on query and body, but not on headers.
+///
+/// Redaction is applied before an interaction is recorded, and to each request
+/// before it is matched during replay, so redacted values still match.
+#[derive(Clone)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: CassetteMode,
+    match_query: bool,
+    match_body: bool,
+    match_headers: bool,
+    ignored_headers: Vec<HeaderName>,
+    redacted_headers: Vec<HeaderName>,
+    redacted_query: Vec<String>,
+    redact: Option<Redact>,
+    state: Arc<Mutex<CassetteState>>,
+}
+
+type Redact = Arc<dyn Fn(&mut Interaction) + Send + Sync>;
+
+/// Whether a Cassette sends requests and records them, or replays them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    Record,
+    Replay,
+}
+
+#[derive(Default)]
+struct CassetteState {
+    interactions: Vec<Interaction>,
+    /// Whether each interaction has been replayed
+    replayed: Vec<bool>,
+}
+
+/// A request and the response that was received for it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: RecordedBody,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub url: String,
+    pub body: RecordedBody,
+}
+
+/// Bodies are stored as text when they are valid utf-8, so that the file is
+/// readable and easy to edit, and as an array of bytes otherwise.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RecordedBody {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl std::fmt::Debug for Cassette {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cassette")
+            .field("path", &self.path)
+            .field("mode", &self.mode)
+            .field("match_query", &self.match_query)
+            .field("match_body", &self.match_body)
+            .field("match_headers", &self.match_headers)
+            .field("ignored_headers", &self.ignored_headers)
+            .field("redacted_headers", &self.redacted_headers)
+            .field("redacted_query", &self.redacted_query)
+            .field("redact", &self.redact.is_some())
+            .finish()
+    }
+}
+
+impl Cassette {
+    /// Send requests normally, and record each request and response. `save`
+    /// writes them to the file, replacing anything that was previously
+    /// recorded there.
+    pub fn record(path: impl AsRef<Path>) -> Self {
+        Self::new(path.as_ref().into(), CassetteMode::Record, vec![])
+    }
+
+    /// Respond to requests with the interactions recorded in the file, without
+    /// sending them. A request that does not match any recorded interaction
+    /// fails with `Error::MiddlewareError`.
+    pub fn replay(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let interactions = serde_json::from_slice(&std::fs::read(path)?)?;
+        Ok(Self::new(path.into(), CassetteMode::Replay, interactions))
+    }
+
+    /// Replay the file if it exists, otherwise record it.
+    pub fn auto(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        if path.as_ref().exists() {
+            Self::replay(path)
+        } else {
+            Ok(Self::record(path))
+        }
+    }
+
+    fn new(path: PathBuf, mode: CassetteMode, interactions: Vec<Interaction>) -> Self {
+        Self {
+            path,
+            mode,
+            match_query: true,
+            match_body: true,
+            match_headers: false,
+            ignored_headers: vec![],
+            redacted_headers: vec![],
+            redacted_query: vec![],
+            redact: None,
+            state: Arc::new(Mutex::new(CassetteState {
+                replayed: vec![false; interactions.len()],
+                interactions,
+            })),
+        }
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    /// Whether a request must have the same query string as the recorded
+    /// request to match it. Enabled by default.
+    pub fn match_query(mut self, match_query: bool) -> Self {
+        self.match_query = match_query;
+        self
+    }
+
+    /// Whether a request must have the same body as the recorded request to
+    /// match it. Enabled by default.
+    pub fn match_body(mut self, match_body: bool) -> Self {
+        self.match_body = match_body;
+        self
+    }
+
+    /// Whether a request must have the same headers as the recorded request
+    /// to match it, except for ignored and redacted headers. Disabled by
+    /// default.
+    pub fn match_headers(mut self, match_headers: bool) -> Self {
+        self.match_headers = match_headers;
+        self
+    }
+
+    /// Exclude a header that changes between runs, such as a timestamp or a
+    /// request id, from matching.
+    ///
+    /// Panics if the name is not a valid header name.
+    pub fn ignore_header(mut self, name: &str) -> Self {
+        self.ignored_headers.push(header_name(name));
+        self
+    }
+
+    /// Replace the value of a request or response header with `[REDACTED]`
+    /// before it is recorded. Redacted headers are not matched.
+    ///
+    /// Panics if the name is not a valid header name.
+    pub fn redact_header(mut self, name: &str) -> Self {
+        self.redacted_headers.push(header_name(name));
+        self
+    }
+
+    /// Replace the value of a query parameter in the request and response urls
+    /// with `[REDACTED]` before it is recorded, such as an api key sent with
+    /// `Auth::api_key_query`. The name is compared before percent-decoding.
+    pub fn redact_query(mut self, name: &str) -> Self {
+        self.redacted_query.push(name.to_owned());
+        self
+    }
+
+    /// Modify each interaction before it is recorded, for example to remove
+    /// secrets from the body.
+    pub fn redact(mut self, redact: impl Fn(&mut Interaction) + Send + Sync + 'static) -> Self {
+        self.redact = Some(Arc::new(redact));
+        self
+    }
+
+    /// The interactions that have been recorded, or loaded for replay.
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.state.lock().unwrap().interactions.clone()
+    }
+
+    /// Write the recorded interactions to the file, creating its directory if
+    /// needed. This does blocking file I/O, so call it after the requests have
+    /// been sent, such as at the end of a test.
+    pub fn save(&self) -> std::io::Result<()> {
+        let json = serde_json::to_vec_pretty(&self.interactions())?;
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, json)
+    }
+
+    fn record_interaction(&self, request: &HttpRequest, response: &HttpResponse) {
+        let mut interaction = Interaction {
+            request: RecordedRequest::new(request),
+            response: RecordedResponse::new(response),
+        };
+        self.apply_redaction(&mut interaction);
+        let mut state = self.state.lock().unwrap();
+        state.interactions.push(interaction);
+        state.replayed.push(false);
+    }
+
+    fn replay_interaction(&self, request: &HttpRequest) -> Result<HttpResponse, BoxError> {
+        let mut interaction = Interaction {
+            request: RecordedRequest::new(request),
+            response: RecordedResponse::default(),
+        };
+        self.apply_redaction(&mut interaction);
+        let request = interaction.request;
+        let mut state = self.state.lock().unwrap();
+        let matching: Vec<usize> = (0..state.interactions.len())
+            .filter(|&i| self.matches(&request, &state.interactions[i].request))
+            .collect();
+        let index = matching
+            .iter()
+            .find(|&&i| !state.replayed[i])
+            .or(matching.last())
+            .copied()
+            .ok_or_else(|| {
+                format!(
+                    "no interaction recorded in {} matches {} {}",
+                    self.path.display(),
+                    request.method,
+                    request.url,
+                )
+            })?;
+        state.replayed[index] = true;
+        state.interactions[index].response.to_http_response()
+    }
+
+    fn apply_redaction(&self, interaction: &mut Interaction) {
+        for (name, value) in interaction
+            .request
+            .headers
+            .iter_mut()
+            .chain(interaction.response.headers.iter_mut())
+        {
+            if self.redacted_headers.iter().any(|h| h == name.as_str()) {
+                *value = "[REDACTED]".into();
+            }
+        }
+        if !self.redacted_query.is_empty() {
+            for url in [&mut interaction.request.url, &mut interaction.response.url] {
+                *url = redact_query(url, &self.redacted_query);
+            }
+        }
+        if let Some(redact) = &self.redact {
+            redact(interaction);
+        }
+    }
+
+    fn matches(&self, request: &RecordedRequest, recorded: &RecordedRequest) -> bool {
+        let (path, query) = split_url(&request.url);
+        let (recorded_path, recorded_query) = split_url(&recorded.url);
+        request.method == recorded.method
+            && path == recorded_path
+            && (!self.match_query || query == recorded_query)
+            && (!self.match_body || request.body == recorded.body)
+            && (!self.match_headers
+                || self.matched_headers(&request.headers)
+                    == self.matched_headers(&recorded.headers))
+    }
+
+    fn matched_headers<'a>(&self, headers: &'a [(String, String)]) -> Vec<&'a (String, String)> {
+        let mut headers: Vec<_> = headers
+            .iter()
+            .filter(|(name, _)| {
+                !self
+                    .ignored_headers
+                    .iter()
+                    .chain(&self.redacted_headers)
+                    .any(|h| h == name.as_str())
+            })
+            .collect();
+        headers.sort();
+        headers
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for Cassette {
+    async fn handle(&self, request: HttpRequest, next: Next<'_>) -> Result<HttpResponse, BoxError> {
+        match self.mode {
+            CassetteMode::Record => {
+                let response = next.run(request.clone()).await?;
+                self.record_interaction(&request, &response);
+                Ok(response)
+            }
+            CassetteMode::Replay => self.replay_interaction(&request),
+        }
+    }
+}
+
+impl RecordedRequest {
+    fn new(request: &HttpRequest) -> Self {
+        Self {
+            method: http::Method::from(request.method).to_string(),
+            url: request.url.clone(),
+            headers: header_pairs(&request.headers),
+            body: RecordedBody::new(&request.body),
+        }
+    }
+}
+
+impl Default for RecordedResponse {
+    fn default() -> Self {
+        Self {
+            status: 200,
+            headers: vec![],
+            url: String::new(),
+            body: RecordedBody::Text(String::new()),
+        }
+    }
+}
+
+impl RecordedResponse {
+    fn new(response: &HttpResponse) -> Self {
+        Self {
+            status: response.status.as_u16(),
+            headers: header_pairs(&response.headers),
+            url: response.url.clone(),
+            body: RecordedBody::new(&response.body),
+        }
+    }
+
+    fn to_http_response(&self) -> Result<HttpResponse, BoxError> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            headers.append(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+        Ok(HttpResponse {
+            status: StatusCode::from_u16(self.status)?,
+            headers,
+            url: self.url.clone(),
+            body: self.body.to_bytes(),
+        })
+    }
+}
+
+impl RecordedBody {
+    fn new(body: &[u8]) -> Self {
+        match std::str::from_utf8(body) {
+            Ok(text) => Self::Text(text.into()),
+            Err(_) => Self::Bytes(body.to_vec()),
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Text(text) => text.as_bytes().to_vec(),
+            Self::Bytes(bytes) => bytes.clone(),
+        }
+    }
+}
+
+fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+            (name.as_str().to_owned(), value)
+        })
+        .collect()
+}
+
+fn header_name(name: &str) -> HeaderName {
+    HeaderName::from_bytes(name.as_bytes())
+        .unwrap_or_else(|_| panic!("invalid header name: {name}"))
+}
+
+/// Replaces the values of the named query parameters, keeping the rest of the
+/// url as it is.
+fn redact_query(url: &str, names: &[String]) -> String {
+    let (url, fragment) = match url.split_once('#') {
+        Some((url, fragment)) => (url, Some(fragment)),
+        None => (url, None),
+    };
+    let mut redacted = match url.split_once('?') {
+        Some((path, query)) => {
+            let query: Vec<_> = query
+                .split('&')
+                .map(|pair| {
+                    let name = pair.split_once('=').map_or(pair, |(name, _)| name);
+                    if names.iter().any(|redacted| redacted == name) {
+                        format!("{name}=[REDACTED]")
+                    } else {
+                        pair.to_owned()
+                    }
+                })
+                .collect();
+            format!("{path}?{}", query.join("&"))
+        }
+        None => url.to_owned(),
+    };
+    if let Some(fragment) = fragment {
+        redacted.push('#');
+        redacted.push_str(fragment);
+    }
+    redacted
+}
+
+/// Splits a url into the path and query, discarding the scheme and host so
+/// that recordings can be replayed against a different server.
+fn split_url(url: &str) -> (&str, Option<&str>) {
+    let path = match url.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("/", |start| &rest[start..]),
+        None => url,
+    };
+    match path.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (path, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testing::{Ping, Stub},
+        All, Auth, Client, Error, HttpMethod,
+    };
+
+    fn client(transport: Stub, cassette: &Cassette) -> Client<All, Stub> {
+        Client::with_inner("http://example.com".into(), transport)
+            .unwrap()
+            .with(cassette.clone())
+    }
+
+    fn request(method: HttpMethod, url: &str, headers: &[(&str, &str)], body: &str) -> HttpRequest {
+        let mut header_map = HeaderMap::new();
+        for (name, value) in headers {
+            header_map.append(header_name(name), HeaderValue::from_str(value).unwrap());
+        }
+        HttpRequest {
+            method,
+            url: url.into(),
+            headers: header_map,
+            body: body.into(),
+        }
+    }
+
+    fn get(url: &str) -> HttpRequest {
+        request(HttpMethod::Get, url, &[], "")
+    }
+
+    /// A cassette in replay mode that responds to each request with the body.
+    fn replaying(interactions: &[(HttpRequest, &str)]) -> Cassette {
+        let interactions = interactions
+            .iter()
+            .map(|(request, body)| Interaction {
+                request: RecordedRequest::new(request),
+                response: RecordedResponse {
+                    body: RecordedBody::Text(body.to_string()),
+                    ..Default::default()
+                },
+            })
+            .collect();
+        Cassette::new("cassette.json".into(), CassetteMode::Replay, interactions)
+    }
+
+    fn replay(cassette: &Cassette, request: &HttpRequest) -> Option<String> {
+        let response = cassette.replay_interaction(request).ok()?;
+        Some(String::from_utf8(response.body).unwrap())
+    }
+
+    #[test]
+    fn matches_method_and_path_but_not_host() {
+        let cassette = replaying(&[(get("http://example.com/p?x=1"), "recorded")]);
+        let replayed = replay(&cassette, &get("http://localhost:8080/p?x=1"));
+        assert_eq!(replayed.as_deref(), Some("recorded"));
+        assert_eq!(replay(&cassette, &get("http://example.com/q?x=1")), None);
+        let delete = request(HttpMethod::Delete, "http://example.com/p?x=1", &[], "");
+        assert_eq!(replay(&cassette, &delete), None);
+    }
+
+    #[test]
+    fn match_query() {
+        let cassette = replaying(&[(get("http://example.com/p?x=1"), "recorded")]);
+        assert_eq!(replay(&cassette, &get("http://example.com/p?x=2")), None);
+        assert_eq!(replay(&cassette, &get("http://example.com/p")), None);
+        let cassette = cassette.match_query(false);
+        assert!(replay(&cassette, &get("http://example.com/p?x=2")).is_some());
+        assert!(replay(&cassette, &get("http://example.com/p")).is_some());
+    }
+
+    #[test]
+    fn match_body() {
+        let post = |body| request(HttpMethod::Post, "http://example.com/p", &[], body);
+        let cassette = replaying(&[(post("a"), "recorded")]);
+        assert!(replay(&cassette, &post("a")).is_some());
+        assert_eq!(replay(&cassette, &post("b")), None);
+        let cassette = cassette.match_body(false);
+        assert!(replay(&cassette, &post("b")).is_some());
+    }
+
+    #[test]
+    fn match_headers() {
+        let url = "http://example.com/p";
+        let recorded = request(
+            HttpMethod::Get,
+            url,
+            &[("accept", "text/plain"), ("x-request-id", "1")],
+            "",
+        );
+        let cassette = replaying(&[(recorded, "recorded")]);
+        let other_accept = request(
+            HttpMethod::Get,
+            url,
+            &[("accept", "application/json"), ("x-request-id", "1")],
+            "",
+        );
+        let other_id = request(
+            HttpMethod::Get,
+            url,
+            &[("x-request-id", "2"), ("accept", "text/plain")],
+            "",
+        );
+        // headers are not matched by default
+        assert!(replay(&cassette, &other_accept).is_some());
+        assert!(replay(&cassette, &other_id).is_some());
+
+        let cassette = cassette.match_headers(true);
+        assert_eq!(replay(&cassette, &other_accept), None);
+        assert_eq!(replay(&cassette, &other_id), None);
+        let cassette = cassette.ignore_header("X-Request-Id");
+        assert_eq!(replay(&cassette, &other_accept), None);
+        assert!(replay(&cassette, &other_id).is_some());
+    }
+
+    #[test]
+    fn repeated_requests_are_replayed_in_order() {
+        let url = "http://example.com/p";
+        let cassette = replaying(&[
+            (get(url), "first"),
+            (get("http://example.com/other"), "other"),
+            (get(url), "second"),
+        ]);
+        assert_eq!(replay(&cassette, &get(url)).as_deref(), Some("first"));
+        assert_eq!(replay(&cassette, &get(url)).as_deref(), Some("second"));
+        // the last match is replayed again once they have all been replayed
+        assert_eq!(replay(&cassette, &get(url)).as_deref(), Some("second"));
+    }
+
+    #[tokio::test]
+    async fn unmatched_request_is_a_middleware_error() {
+        let cassette = replaying(&[(get("http://example.com/other"), "other")]);
+        let client = client(Stub::new(|_| Err("offline".into())), &cassette);
+        match client.send(Ping).await {
+            Err(Error::MiddlewareError(error)) => assert_eq!(
+                error.to_string(),
+                "no interaction recorded in cassette.json matches GET http://example.com/ping"
+            ),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn auto_records_then_replays() {
+        let path = std::env::temp_dir().join(format!(
+            "http-typed-cassette-auto-{}.json",
+            std::process::id()
+        ));
+        let cassette = Cassette::auto(&path).unwrap();
+        assert_eq!(cassette.mode(), CassetteMode::Record);
+        let recording = client(Stub::ok(), &cassette);
+        assert_eq!(recording.send(Ping).await.unwrap(), "pong");
+        cassette.save().unwrap();
+
+        let cassette = Cassette::auto(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(cassette.mode(), CassetteMode::Replay);
+        assert_eq!(cassette.interactions().len(), 1);
+        let client = client(Stub::new(|_| Err("offline".into())), &cassette);
+        assert_eq!(client.send(Ping).await.unwrap(), "pong");
+    }
+
+    #[test]
+    fn redacts_only_named_query_parameters() {
+        let names = ["key".to_owned()];
+        let redact = |url| redact_query(url, &names);
+        assert_eq!(
+            redact("http://h/p?a=1&key=s3cret&b=2#f"),
+            "http://h/p?a=1&key=[REDACTED]&b=2#f"
+        );
+        assert_eq!(redact("http://h/p?key"), "http://h/p?key=[REDACTED]");
+        assert_eq!(redact("http://h/p?keys=1"), "http://h/p?keys=1");
+        assert_eq!(redact("http://h/key=1"), "http://h/key=1");
+    }
+
+    #[tokio::test]
+    async fn recording_has_no_credentials() {
+        let path =
+            std::env::temp_dir().join(format!("http-typed-cassette-{}.json", std::process::id()));
+        let cassette = Cassette::record(&path)
+            .redact_query("api_key")
+            .redact_header("authorization");
+        for auth in [
+            Auth::api_key_query("api_key", "s3cret"),
+            Auth::bearer("s3cret"),
+        ] {
+            let client = client(Stub::ok(), &cassette).auth(auth);
+            assert_eq!(client.send(Ping).await.unwrap(), "pong");
+        }
+        assert!(!path.exists(), "nothing is written before save");
+        cassette.save().unwrap();
+        let recorded = std::fs::read_to_string(&path).unwrap();
+        assert!(!recorded.contains("s3cret"), "{recorded}");
+        assert!(recorded.contains("api_key=[REDACTED]"));
+
+        // redacted requests still match during replay
+        let replay = Cassette::replay(&path)
+            .unwrap()
+            .redact_query("api_key")
+            .redact_header("authorization");
+        std::fs::remove_file(&path).unwrap();
+        let offline = || Stub::new(|_| Err("offline".into()));
+        for auth in [
+            Auth::api_key_query("api_key", "other"),
+            Auth::bearer("other"),
+        ] {
+            let client = client(offline(), &replay).auth(auth);
+            assert_eq!(client.send(Ping).await.unwrap(), "pong");
+        }
+    }
+}
@@ -1,77 +1,225 @@
-use std::{any::type_name, marker::PhantomData};
+use std::{
+    any::type_name,
+    marker::PhantomData,
+    sync::{atomic::AtomicU8, Arc},
+    time::{Duration, Instant},
+};
 
-use reqwest::header::CONTENT_TYPE;
+use http::{
+    header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE},
+    StatusCode,
+};
+pub use url::Url;
 
-use crate::{All, HttpMethod, InRequestGroup, Request, SerializeBody};
+use crate::{
+    auth::Auth,
+    middleware::{BoxError, HttpRequest, HttpResponse, Middleware, Next, SendError, Signer},
+    retry::RetryPolicy,
+    transport::{DefaultTransport, Transport},
+    All, DeserializeBody, HttpMethod, InRequestGroup, Request, SerdeJson, SerializeBody,
+};
 
 /// A client to delegate to the send function that provides the ability to
 /// optionally specify:
-/// - a base url to be used for all requests
+/// - a base url to be used for all requests, which is validated when the
+///   client is created (see `Client::url` for how it is joined with paths)
 /// - a request group to constrain the request types accepted by this type
-pub struct Client<RequestGroup = All> {
-    base_url: String,
-    inner: reqwest::Client,
+/// - a transport to send the http requests, which is reqwest by default
+pub struct Client<RequestGroup = All, T = DefaultTransport> {
+    base_url: Option<Url>,
+    inner: T,
+    middleware: Vec<Arc<dyn Middleware>>,
+    retry: Option<RetryPolicy>,
+    auth: Option<Auth>,
+    signer: Option<Arc<dyn Signer>>,
     _p: PhantomData<RequestGroup>,
 }
 
 /// Explicitly implemented to avoid requirement RequestGroup: Debug
-impl<RequestGroup> std::fmt::Debug for Client<RequestGroup> {
+impl<RequestGroup, T: std::fmt::Debug> std::fmt::Debug for Client<RequestGroup, T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct(type_name::<Self>())
-            .field("base_url", &self.base_url)
+            .field("base_url", &self.base_url.as_ref().map(Url::as_str))
             .field("inner", &self.inner)
+            .field("middleware", &self.middleware.len())
+            .field("retry", &self.retry)
+            .field("auth", &self.auth)
+            .field("signer", &self.signer.is_some())
             .finish()
     }
 }
 
 /// Explicitly implemented to avoid requirement RequestGroup: Default
-impl<RequestGroup> Default for Client<RequestGroup> {
+impl<RequestGroup, T: Default> Default for Client<RequestGroup, T> {
     fn default() -> Self {
         Self {
             base_url: Default::default(),
             inner: Default::default(),
+            middleware: vec![],
+            retry: None,
+            auth: None,
+            signer: None,
             _p: PhantomData,
         }
     }
 }
 
 /// Explicitly implemented to avoid requirement RequestGroup: Clone
-impl<RequestGroup> Clone for Client<RequestGroup> {
+impl<RequestGroup, T: Clone> Clone for Client<RequestGroup, T> {
     fn clone(&self) -> Self {
         Self {
             base_url: self.base_url.clone(),
             inner: self.inner.clone(),
+            middleware: self.middleware.clone(),
+            retry: self.retry.clone(),
+            auth: self.auth.clone(),
+            signer: self.signer.clone(),
             _p: PhantomData,
         }
     }
 }
 
-impl<RequestGroup> Client<RequestGroup> {
-    pub fn new(base_url: String) -> Self {
-        Self {
-            base_url,
-            inner: reqwest::Client::new(),
+#[cfg(feature = "client")]
+impl<RequestGroup> Client<RequestGroup, reqwest::Client> {
+    /// Returns an error if base_url is not a valid absolute url. An empty
+    /// base_url is allowed, and means that the full url must be provided to
+    /// `send_to` or `send_custom`.
+    pub fn new(base_url: String) -> Result<Self, UrlError> {
+        Self::with_inner(base_url, reqwest::Client::new())
+    }
+
+    /// Configure a client with default headers, timeouts, and other settings.
+    pub fn builder() -> ClientBuilder<RequestGroup> {
+        ClientBuilder::default()
+    }
+}
+
+impl<RequestGroup, T: Transport> Client<RequestGroup, T> {
+    /// Use a pre-configured transport to send requests, such as a
+    /// `reqwest::Client`. The base_url is validated the same as in `new`.
+    pub fn with_inner(base_url: String, inner: T) -> Result<Self, UrlError> {
+        Ok(Self {
+            base_url: parse_base_url(&base_url)?,
+            inner,
+            middleware: vec![],
+            retry: None,
+            auth: None,
+            signer: None,
             _p: PhantomData,
-        }
+        })
+    }
+
+    /// Add a middleware that runs around every request sent by this client.
+    /// Middleware runs in the order it was added, so the first middleware
+    /// added is the outermost.
+    pub fn with(mut self, middleware: impl Middleware) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Retry idempotent requests that fail with a transient error. Each
+    /// attempt runs through the middleware again.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Authenticate every request that does not opt out with
+    /// `Request::authenticate`. The credentials are added before the request
+    /// is passed to the middleware.
+    pub fn auth(mut self, auth: Auth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Sign every request immediately before it is sent, after the
+    /// middleware has run.
+    pub fn signer(mut self, signer: impl Signer) -> Self {
+        self.signer = Some(Arc::new(signer));
+        self
+    }
+
+    /// The url that a request with the given path is sent to, after inserting
+    /// url_infix as in `send_to`. Use "" as the url_infix for `send`.
+    ///
+    /// The parts are joined with these rules:
+    /// - Paths are appended to the base url's path, even if they start with
+    ///   `/`. Exactly one `/` separates each part, and a trailing `/` is kept
+    ///   only if the last non-empty part has one.
+    /// - Query strings in the base url, the url_infix, and the path are all
+    ///   kept, and are combined in that order.
+    /// - `.` and `..` segments in the url_infix or path are an error instead of
+    ///   being resolved, so that a value in the path cannot change which
+    ///   resource is requested. This includes their percent-encoded forms, such
+    ///   as `%2E%2E`, which urls also resolve.
+    /// - Characters that are not allowed in a url are percent-encoded.
+    /// - If the client has no base url, the url_infix must be an absolute url,
+    ///   or the path must be if the url_infix is "".
+    ///
+    /// ```
+    /// # use http_typed::{All, Client};
+    /// let client = Client::<All>::new("http://example.com/api/".into())?;
+    /// assert_eq!(client.url("", "/users")?.as_str(), "http://example.com/api/users");
+    /// assert_eq!(client.url("/v2/", "/users/")?.as_str(), "http://example.com/api/v2/users/");
+    /// assert_eq!(client.url("", "")?.as_str(), "http://example.com/api/");
+    /// assert!(client.url("v2", "users/../teams").is_err());
+    ///
+    /// let client = Client::<All>::new("http://example.com/api?key=abc".into())?;
+    /// assert_eq!(
+    ///     client.url("", "/users?page=2")?.as_str(),
+    ///     "http://example.com/api/users?key=abc&page=2"
+    /// );
+    ///
+    /// let client = Client::<All>::default();
+    /// assert_eq!(client.url("http://example.com", "/users")?.as_str(), "http://example.com/users");
+    /// assert_eq!(client.url("", "http://example.com/users")?.as_str(), "http://example.com/users");
+    /// assert!(client.url("", "/users").is_err());
+    ///
+    /// assert!(Client::<All>::new("example.com".into()).is_err());
+    /// assert!(Client::<All>::new("http://example.com/#users".into()).is_err());
+    /// # Ok::<_, http_typed::UrlError>(())
+    /// ```
+    pub fn url(&self, url_infix: &str, path: &str) -> Result<Url, UrlError> {
+        join_url(self.base_url.as_ref(), url_infix, path)
     }
 
     /// Send the provided request to the host at this client's base_url, using
     /// the Request implementation to determine the remaining url path and
     /// request data.
     ///
-    /// The url used for the request is {self.base_url}{request.path()}
-    pub async fn send<Req>(
+    /// The url used for the request is {self.base_url}{request.path()}, joined
+    /// as described in `Client::url`.
+    pub async fn send<Req>(&self, request: Req) -> Result<Req::Response, RequestError<Req>>
+    where
+        Req: Request + InRequestGroup<RequestGroup>,
+        Req::ErrorResponse: for<'a> serde::Deserialize<'a>,
+    {
+        self.send_simple::<_, _, _, Req::Deserializer>(
+            self.url("", &request.path())?,
+            request.method(),
+            request.idempotent(),
+            request.authenticate(),
+            request,
+        )
+        .await
+        .map(|response| response.value)
+    }
+
+    /// Same as `send`, but also returns the status code, headers, and other
+    /// metadata about the http response.
+    pub async fn send_with_meta<Req>(
         &self,
         request: Req,
-    ) -> Result<Req::Response, Error<<Req::Serializer as SerializeBody<Req>>::Error>>
+    ) -> Result<Response<Req::Response>, RequestError<Req>>
     where
         Req: Request + InRequestGroup<RequestGroup>,
-        Req::Response: for<'a> serde::Deserialize<'a>,
+        Req::ErrorResponse: for<'a> serde::Deserialize<'a>,
     {
-        send_custom_with_client(
-            &self.inner,
-            &format!("{}{}", self.base_url, request.path()),
+        self.send_simple::<_, _, _, Req::Deserializer>(
+            self.url("", &request.path())?,
             request.method(),
+            request.idempotent(),
+            request.authenticate(),
             request,
         )
         .await
@@ -82,7 +230,8 @@ impl<RequestGroup> Client<RequestGroup> {
     /// url path and request data.
     ///
     /// The url used for the request is
-    /// {self.base_url}{url_infix}{request.path()}
+    /// {self.base_url}{url_infix}{request.path()}, joined as described in
+    /// `Client::url`.
     ///
     /// If you'd like to specify the entire base url for each request using this
     /// method, instantiate this struct with base_url = "" (the default)
@@ -90,15 +239,38 @@ impl<RequestGroup> Client<RequestGroup> {
         &self,
         url_infix: &str,
         request: Req,
-    ) -> Result<Req::Response, Error<<Req::Serializer as SerializeBody<Req>>::Error>>
+    ) -> Result<Req::Response, RequestError<Req>>
     where
         Req: Request + InRequestGroup<RequestGroup>,
-        Req::Response: for<'a> serde::Deserialize<'a>,
+        Req::ErrorResponse: for<'a> serde::Deserialize<'a>,
     {
-        send_custom_with_client(
-            &self.inner,
-            &format!("{}{url_infix}{}", self.base_url, request.path()),
+        self.send_simple::<_, _, _, Req::Deserializer>(
+            self.url(url_infix, &request.path())?,
             request.method(),
+            request.idempotent(),
+            request.authenticate(),
+            request,
+        )
+        .await
+        .map(|response| response.value)
+    }
+
+    /// Same as `send_to`, but also returns the status code, headers, and other
+    /// metadata about the http response.
+    pub async fn send_to_with_meta<Req>(
+        &self,
+        url_infix: &str,
+        request: Req,
+    ) -> Result<Response<Req::Response>, RequestError<Req>>
+    where
+        Req: Request + InRequestGroup<RequestGroup>,
+        Req::ErrorResponse: for<'a> serde::Deserialize<'a>,
+    {
+        self.send_simple::<_, _, _, Req::Deserializer>(
+            self.url(url_infix, &request.path())?,
+            request.method(),
+            request.idempotent(),
+            request.authenticate(),
             request,
         )
         .await
@@ -107,7 +279,8 @@ impl<RequestGroup> Client<RequestGroup> {
     /// Send the provided request to the specified path using the specified method,
     /// and deserialize the response into the specified response type.
     ///
-    /// The url used for this request is {self.base_url}{path}
+    /// The url used for this request is {self.base_url}{path}, joined as
+    /// described in `Client::url`.
     ///
     /// If you'd like to specify the entire base url for each request using this
     /// method, instantiate this struct with base_url = "" (the default)
@@ -121,13 +294,234 @@ impl<RequestGroup> Client<RequestGroup> {
         Req: SimpleBody,
         Res: for<'a> serde::Deserialize<'a>,
     {
-        send_custom_with_client(
-            &self.inner,
-            &format!("{}{path}", self.base_url),
+        self.send_simple::<_, _, _, SerdeJson>(
+            self.url("", path)?,
             method,
+            method.is_idempotent(),
+            true,
             request,
         )
         .await
+        .map(|response| response.value)
+    }
+
+    async fn send_simple<Req, Res, ErrRes, De>(
+        &self,
+        url: Url,
+        method: HttpMethod,
+        idempotent: bool,
+        authenticate: bool,
+        request: Req,
+    ) -> Result<Response<Res>, Error<Req::Error, ErrRes, De::Error>>
+    where
+        Req: SimpleBody,
+        ErrRes: for<'a> serde::Deserialize<'a>,
+        De: DeserializeBody<Res>,
+    {
+        let request = http_request(url, method, &request, De::accept())?;
+        let start = Instant::now();
+        let send = |request| self.run(request, idempotent);
+        let response = match self.auth.as_ref().filter(|_| authenticate) {
+            Some(auth) => auth.send(request, send).await?,
+            None => send(request).await.map_err(send_error)?,
+        };
+        let elapsed = start.elapsed();
+        let value = deserialize_response::<_, _, _, De>(&response)?;
+        Ok(Response {
+            value,
+            status: response.status,
+            headers: response.headers,
+            url: response.url,
+            elapsed,
+        })
+    }
+
+    /// Send the request through the middleware, retrying if allowed.
+    async fn run(&self, request: HttpRequest, idempotent: bool) -> Result<HttpResponse, SendError> {
+        let stage = AtomicU8::new(0);
+        let next = Next::new(
+            &self.inner,
+            &self.middleware,
+            self.signer.as_deref(),
+            &stage,
+        );
+        match &self.retry {
+            Some(policy) if idempotent => policy.run(next, request).await,
+            _ => next.send(request).await,
+        }
+    }
+}
+
+/// A deserialized response body, along with metadata about the http response.
+#[derive(Debug, Clone)]
+pub struct Response<T> {
+    pub value: T,
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    /// The final url of the response, after any redirects.
+    pub url: String,
+    /// Time from when the request was sent until the response body was
+    /// received, including any retries. Serializing the request and
+    /// deserializing the response are not included.
+    pub elapsed: Duration,
+}
+
+pub(crate) fn send_error<Ser, ErrRes, De>(error: SendError) -> Error<Ser, ErrRes, De> {
+    match error {
+        SendError::Middleware(error) => Error::MiddlewareError(error),
+        SendError::Signer(error) => Error::AuthError(error),
+        SendError::Transport(error) => transport_error(error),
+    }
+}
+
+#[cfg(feature = "client")]
+fn transport_error<Ser, ErrRes, De>(error: BoxError) -> Error<Ser, ErrRes, De> {
+    match error.downcast::<reqwest::Error>() {
+        Ok(error) => Error::ClientError(*error),
+        Err(error) => Error::TransportError(error),
+    }
+}
+
+#[cfg(not(feature = "client"))]
+fn transport_error<Ser, ErrRes, De>(error: BoxError) -> Error<Ser, ErrRes, De> {
+    Error::TransportError(error)
+}
+
+/// Builds a Client with settings that apply to every request it sends.
+///
+/// ```ignore
+/// let client = Client::<MyApi>::builder()
+///     .base_url("http://example.com")
+///     .timeout(Duration::from_secs(10))
+///     .user_agent("my-service/1.0")
+///     .build()?;
+/// ```
+#[cfg(feature = "client")]
+pub struct ClientBuilder<RequestGroup = All> {
+    base_url: String,
+    inner: reqwest::ClientBuilder,
+    middleware: Vec<Arc<dyn Middleware>>,
+    retry: Option<RetryPolicy>,
+    auth: Option<Auth>,
+    signer: Option<Arc<dyn Signer>>,
+    _p: PhantomData<RequestGroup>,
+}
+
+/// Explicitly implemented to avoid requirement RequestGroup: Debug
+#[cfg(feature = "client")]
+impl<RequestGroup> std::fmt::Debug for ClientBuilder<RequestGroup> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(type_name::<Self>())
+            .field("base_url", &self.base_url)
+            .field("inner", &self.inner)
+            .field("middleware", &self.middleware.len())
+            .field("retry", &self.retry)
+            .field("auth", &self.auth)
+            .field("signer", &self.signer.is_some())
+            .finish()
+    }
+}
+
+/// Explicitly implemented to avoid requirement RequestGroup: Default
+#[cfg(feature = "client")]
+impl<RequestGroup> Default for ClientBuilder<RequestGroup> {
+    fn default() -> Self {
+        Self {
+            base_url: Default::default(),
+            inner: reqwest::Client::builder(),
+            middleware: vec![],
+            retry: None,
+            auth: None,
+            signer: None,
+            _p: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+impl<RequestGroup> ClientBuilder<RequestGroup> {
+    /// Base url to be used for all requests. Defaults to "". It is validated
+    /// by `build`, the same as in `Client::new`.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Headers to send with every request. Headers from `Request::headers`
+    /// take precedence over these.
+    pub fn default_headers(self, headers: HeaderMap) -> Self {
+        self.configure(|inner| inner.default_headers(headers))
+    }
+
+    /// Value of the User-Agent header sent with every request.
+    pub fn user_agent(self, user_agent: &str) -> Self {
+        let user_agent = user_agent.to_owned();
+        self.configure(|inner| inner.user_agent(user_agent))
+    }
+
+    /// Timeout for the entire request, from connecting until the response
+    /// body has been received.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.configure(|inner| inner.timeout(timeout))
+    }
+
+    /// Timeout for only the connect phase of a request.
+    pub fn connect_timeout(self, timeout: Duration) -> Self {
+        self.configure(|inner| inner.connect_timeout(timeout))
+    }
+
+    /// How long idle sockets are kept alive in the connection pool. None
+    /// disables the timeout.
+    pub fn pool_idle_timeout(self, timeout: Option<Duration>) -> Self {
+        self.configure(|inner| inner.pool_idle_timeout(timeout))
+    }
+
+    /// Maximum number of idle connections kept in the pool for each host.
+    pub fn pool_max_idle_per_host(self, max: usize) -> Self {
+        self.configure(|inner| inner.pool_max_idle_per_host(max))
+    }
+
+    /// Apply any other settings directly to the underlying reqwest builder.
+    pub fn configure(
+        mut self,
+        f: impl FnOnce(reqwest::ClientBuilder) -> reqwest::ClientBuilder,
+    ) -> Self {
+        self.inner = f(self.inner);
+        self
+    }
+
+    /// Add a middleware that runs around every request. See `Client::with`.
+    pub fn with(mut self, middleware: impl Middleware) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Retry idempotent requests that fail with a transient error. See
+    /// `Client::retry`.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Authenticate every request. See `Client::auth`.
+    pub fn auth(mut self, auth: Auth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Sign every request. See `Client::signer`.
+    pub fn signer(mut self, signer: impl Signer) -> Self {
+        self.signer = Some(Arc::new(signer));
+        self
+    }
+
+    pub fn build(self) -> Result<Client<RequestGroup, reqwest::Client>, Error> {
+        let mut client = Client::with_inner(self.base_url, self.inner.build()?)?;
+        client.middleware = self.middleware;
+        client.retry = self.retry;
+        client.auth = self.auth;
+        client.signer = self.signer;
+        Ok(client)
     }
 }
 
@@ -143,17 +537,17 @@ impl<RequestGroup> Client<RequestGroup> {
 /// request metadata specified by the Request implementation to create the http
 /// request and determine the response type.
 ///
-/// The url used for the request is {base_url}{request.path()}
-pub async fn send<Req>(
-    base_url: &str,
-    request: Req,
-) -> Result<Req::Response, Error<<Req::Serializer as SerializeBody<Req>>::Error>>
+/// The url used for the request is {base_url}{request.path()}, joined as
+/// described in `Client::url`.
+#[cfg(feature = "client")]
+pub async fn send<Req>(base_url: &str, request: Req) -> Result<Req::Response, RequestError<Req>>
 where
     Req: Request,
-    Req::Response: for<'a> serde::Deserialize<'a>,
+    Req::ErrorResponse: for<'a> serde::Deserialize<'a>,
 {
-    let url = format!("{base_url}{}", request.path());
-    send_custom_with_client(&reqwest::Client::new(), &url, request.method(), request).await
+    Client::<All, reqwest::Client>::new(base_url.to_owned())?
+        .send(request)
+        .await
 }
 
 /// Convenience function to create a client and send a request using minimal
@@ -166,6 +560,7 @@ where
 ///
 /// Send the provided request to the specified url using the specified method,
 /// and deserialize the response into the specified response type.
+#[cfg(feature = "client")]
 pub async fn send_custom<Req, Res>(
     url: &str,
     method: HttpMethod,
@@ -175,39 +570,171 @@ where
     Req: SimpleBody,
     Res: for<'a> serde::Deserialize<'a>,
 {
-    send_custom_with_client(&reqwest::Client::new(), url, method, request).await
+    Client::<All, reqwest::Client>::default()
+        .send_custom(url, method, request)
+        .await
 }
 
-async fn send_custom_with_client<Req, Res>(
-    client: &reqwest::Client,
-    url: &str,
+/// Serialize the request into the http request that will be sent. Accept is
+/// the media type that the response will be deserialized from, if any.
+pub(crate) fn http_request<Req, ErrRes, De>(
+    mut url: Url,
     method: HttpMethod,
-    request: Req,
-) -> Result<Res, Error<Req::Error>>
+    request: &Req,
+    accept: Option<&'static str>,
+) -> Result<HttpRequest, Error<Req::Error, ErrRes, De>>
 where
     Req: SimpleBody,
-    Res: for<'a> serde::Deserialize<'a>,
 {
-    let response = client
-        .request(method.into(), url)
-        .body(request.simple_body().map_err(Error::SerializationError)?)
-        .header(CONTENT_TYPE, "application/json")
-        .send()
-        .await?;
-    let status = response.status();
+    if let Some(query) = request.simple_query().map_err(Error::SerializationError)? {
+        append_query(&mut url, &query);
+    }
+    let (body, content_type) = request
+        .simple_body_and_content_type()
+        .map_err(Error::SerializationError)?;
+    let mut headers = HeaderMap::new();
+    if let Some(accept) = accept {
+        headers.insert(ACCEPT, HeaderValue::from_static(accept));
+    }
+    if let Some(content_type) = content_type.filter(|_| !body.is_empty()) {
+        let content_type = HeaderValue::from_str(&content_type)
+            .map_err(|_| Error::InvalidHeader(CONTENT_TYPE.to_string()))?;
+        headers.insert(CONTENT_TYPE, content_type);
+    }
+    headers.extend(header_map(request.simple_headers())?);
+    Ok(HttpRequest {
+        method,
+        url: url.into(),
+        headers,
+        body,
+    })
+}
+
+/// Deserialize the response body according to its status code.
+pub(crate) fn deserialize_response<Ser, Res, ErrRes, De>(
+    response: &HttpResponse,
+) -> Result<Res, Error<Ser, ErrRes, De::Error>>
+where
+    ErrRes: for<'a> serde::Deserialize<'a>,
+    De: DeserializeBody<Res>,
+{
+    let status = response.status;
+    let body = &response.body;
     if status.is_success() {
-        let body = response.bytes().await?;
-        serde_json::from_slice(&body).map_err(|error| Error::DeserializationError {
+        De::deserialize_body(body).map_err(|error| Error::DeserializationError {
             error,
-            response_body: body_bytes_to_str(&body),
+            response_body: body_bytes_to_str(body),
         })
     } else {
-        let message = match response.bytes().await {
-            Ok(bytes) => body_bytes_to_str(&bytes),
-            Err(e) => format!("failed to get body: {e:?}"),
+        match De::deserialize_error(body) {
+            Some(body) => Err(Error::Api {
+                status: status.into(),
+                headers: response.headers.clone(),
+                body,
+            }),
+            None => Err(Error::InvalidStatusCode(
+                status.into(),
+                body_bytes_to_str(body),
+            )),
+        }
+    }
+}
+
+/// Validate a base url. An empty string is no base url.
+pub(crate) fn parse_base_url(base_url: &str) -> Result<Option<Url>, UrlError> {
+    if base_url.is_empty() {
+        return Ok(None);
+    }
+    let url = Url::parse(base_url).map_err(|error| UrlError::new(base_url, error))?;
+    if url.cannot_be_a_base() {
+        return Err(UrlError::new(base_url, "url cannot have a path appended"));
+    }
+    if url.fragment().is_some() {
+        return Err(UrlError::new(base_url, "base url cannot have a fragment"));
+    }
+    Ok(Some(url))
+}
+
+/// Join the base url, infix, and path. See `Client::url` for the rules.
+pub(crate) fn join_url(base: Option<&Url>, infix: &str, path: &str) -> Result<Url, UrlError> {
+    for part in [infix, path] {
+        if has_dot_segment(part) {
+            return Err(UrlError::new(part, "path cannot have `.` or `..` segments"));
+        }
+    }
+    let (mut url, parts) = match (base, infix.is_empty()) {
+        (Some(base), _) => (base.clone(), [infix, path]),
+        (None, false) => (parse_base_url(infix)?.ok_or_else(no_base)?, ["", path]),
+        (None, true) => (parse_base_url(path)?.ok_or_else(no_base)?, ["", ""]),
+    };
+    let mut joined_path = url.path().to_owned();
+    let mut queries: Vec<&str> = url.query().into_iter().collect();
+    for part in parts {
+        let (part_path, query) = match part.split_once('?') {
+            Some((part_path, query)) => (part_path, Some(query)),
+            None => (part, None),
         };
-        Err(Error::InvalidStatusCode(status.into(), message))
+        if !part_path.is_empty() {
+            joined_path.truncate(joined_path.trim_end_matches('/').len());
+            joined_path.push('/');
+            joined_path.push_str(part_path.trim_start_matches('/'));
+        }
+        queries.extend(query);
+    }
+    let query = queries
+        .into_iter()
+        .filter(|query| !query.is_empty())
+        .collect::<Vec<_>>()
+        .join("&");
+    url.set_path(&joined_path);
+    url.set_query((!query.is_empty()).then_some(query.as_str()));
+    Ok(url)
+}
+
+/// Whether the path, before any query string or fragment, has a segment that
+/// a url resolves as the current or parent directory. Like `Url`, this treats
+/// `\` as `/`.
+fn has_dot_segment(path: &str) -> bool {
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    path.split(['/', '\\']).any(|segment| {
+        matches!(
+            segment.to_ascii_lowercase().as_str(),
+            "." | ".." | "%2e" | ".%2e" | "%2e." | "%2e%2e"
+        )
+    })
+}
+
+fn no_base() -> UrlError {
+    UrlError::new(
+        "",
+        "an absolute url is required when the client has no base url",
+    )
+}
+
+/// Add the percent-encoded query string to the url, after its existing query.
+pub(crate) fn append_query(url: &mut Url, query: &str) {
+    if query.is_empty() {
+        return;
+    }
+    let query = match url.query() {
+        Some(existing) if !existing.is_empty() => format!("{existing}&{query}"),
+        _ => query.to_owned(),
+    };
+    url.set_query(Some(&query));
+}
+
+fn header_map<Ser, ErrRes, De>(
+    headers: Vec<(String, String)>,
+) -> Result<HeaderMap, Error<Ser, ErrRes, De>> {
+    let mut map = HeaderMap::with_capacity(headers.len());
+    for (name, value) in headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| Error::InvalidHeader(name.clone()))?;
+        let value =
+            HeaderValue::from_str(&value).map_err(|_| Error::InvalidHeader(name.to_string()))?;
+        map.append(name, value);
     }
+    Ok(map)
 }
 
 /// This allows the send_custom methods to accept objects that do not implement
@@ -217,6 +744,29 @@ where
 pub trait SimpleBody {
     type Error;
     fn simple_body(&self) -> Result<Vec<u8>, Self::Error>;
+
+    /// Percent-encoded query string to append to the url, without the leading
+    /// `?`.
+    fn simple_query(&self) -> Result<Option<String>, Self::Error> {
+        Ok(None)
+    }
+
+    /// Additional http headers to send with the request.
+    fn simple_headers(&self) -> Vec<(String, String)> {
+        vec![]
+    }
+
+    /// Value of the Content-Type header for the body. Defaults to json. It is
+    /// not sent when the body is empty.
+    fn simple_content_type(&self) -> Option<String> {
+        Some("application/json".to_owned())
+    }
+
+    /// The body and its Content-Type. Defaults to calling `simple_body` and
+    /// `simple_content_type`.
+    fn simple_body_and_content_type(&self) -> Result<(Vec<u8>, Option<String>), Self::Error> {
+        Ok((self.simple_body()?, self.simple_content_type()))
+    }
 }
 
 impl<T: Request> SimpleBody for T {
@@ -225,6 +775,22 @@ impl<T: Request> SimpleBody for T {
     fn simple_body(&self) -> Result<Vec<u8>, Self::Error> {
         <Self as Request>::Serializer::serialize_body(self)
     }
+
+    fn simple_query(&self) -> Result<Option<String>, Self::Error> {
+        <Self as Request>::Serializer::serialize_query(self)
+    }
+
+    fn simple_headers(&self) -> Vec<(String, String)> {
+        self.headers()
+    }
+
+    fn simple_content_type(&self) -> Option<String> {
+        <Self as Request>::Serializer::content_type(self)
+    }
+
+    fn simple_body_and_content_type(&self) -> Result<(Vec<u8>, Option<String>), Self::Error> {
+        <Self as Request>::Serializer::serialize_body_and_content_type(self)
+    }
 }
 
 fn body_bytes_to_str(bytes: &[u8]) -> String {
@@ -234,33 +800,498 @@ fn body_bytes_to_str(bytes: &[u8]) -> String {
     }
 }
 
+/// The error returned when sending a request that implements Request.
+pub type RequestError<Req> = Error<
+    <<Req as Request>::Serializer as SerializeBody<Req>>::Error,
+    <Req as Request>::ErrorResponse,
+    <<Req as Request>::Deserializer as DeserializeBody<<Req as Request>::Response>>::Error,
+>;
+
+/// Variants may be added in minor releases, and which variants exist depends on
+/// the enabled features, so matches need a wildcard arm.
 #[derive(thiserror::Error, Debug)]
-pub enum Error<Ser = serde_json::error::Error> {
+#[non_exhaustive]
+pub enum Error<Ser = serde_json::error::Error, ErrRes = String, De = serde_json::error::Error> {
+    #[cfg(feature = "client")]
     #[error("reqwest error: {0}")]
     ClientError(#[from] reqwest::Error),
+    /// Returned by a transport other than reqwest.
+    #[error("transport error: {0}")]
+    TransportError(BoxError),
+    /// Returned by a middleware.
+    #[error("middleware error: {0}")]
+    MiddlewareError(BoxError),
+    /// Returned when credentials from `Auth` could not be added to a request,
+    /// or the `Signer` could not sign it.
+    #[error("authentication error: {0}")]
+    AuthError(BoxError),
+    #[error("invalid http header: {0}")]
+    InvalidHeader(String),
     #[error("request body serialization error: {0}")]
     SerializationError(Ser),
-    #[error("serde deserialization error `{error}` while parsing response body: {response_body}")]
-    DeserializationError {
-        error: serde_json::error::Error,
-        response_body: String,
+    #[error("deserialization error `{error}` while parsing response body: {response_body}")]
+    DeserializationError { error: De, response_body: String },
+    /// The status code was outside the 200 range, and the body was
+    /// deserialized into `Request::ErrorResponse`. The headers often carry
+    /// details such as `Retry-After` or a request id.
+    #[error("api error with status code {status}: {body:?}")]
+    Api {
+        status: u16,
+        headers: HeaderMap,
+        body: ErrRes,
     },
     #[error("invalid status code {0} with response body: `{1}`")]
     InvalidStatusCode(u16, String),
+    #[error("{0}")]
+    InvalidUrl(#[from] UrlError),
 }
 
-impl From<HttpMethod> for reqwest::Method {
+/// A base url that is not valid, or a url that could not be built from the
+/// base url and a request path.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid url `{url}`: {reason}")]
+pub struct UrlError {
+    pub url: String,
+    pub reason: String,
+}
+
+impl UrlError {
+    fn new(url: &str, reason: impl std::fmt::Display) -> Self {
+        Self {
+            url: url.to_owned(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl From<HttpMethod> for http::Method {
     fn from(value: HttpMethod) -> Self {
         match value {
-            HttpMethod::Options => reqwest::Method::OPTIONS,
-            HttpMethod::Get => reqwest::Method::GET,
-            HttpMethod::Post => reqwest::Method::POST,
-            HttpMethod::Put => reqwest::Method::PUT,
-            HttpMethod::Delete => reqwest::Method::DELETE,
-            HttpMethod::Head => reqwest::Method::HEAD,
-            HttpMethod::Trace => reqwest::Method::TRACE,
-            HttpMethod::Connect => reqwest::Method::CONNECT,
-            HttpMethod::Patch => reqwest::Method::PATCH,
+            HttpMethod::Options => http::Method::OPTIONS,
+            HttpMethod::Get => http::Method::GET,
+            HttpMethod::Post => http::Method::POST,
+            HttpMethod::Put => http::Method::PUT,
+            HttpMethod::Delete => http::Method::DELETE,
+            HttpMethod::Head => http::Method::HEAD,
+            HttpMethod::Trace => http::Method::TRACE,
+            HttpMethod::Connect => http::Method::CONNECT,
+            HttpMethod::Patch => http::Method::PATCH,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{response, Ping, Stub};
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Problem {
+        detail: String,
+    }
+
+    #[test]
+    fn error_body_uses_deserializer() {
+        let result = deserialize_response::<(), String, Problem, SerdeJson>(&response(
+            400,
+            br#"{"detail":"x"}"#,
+        ));
+        assert!(matches!(
+            result,
+            Err(Error::Api { status: 400, body, .. }) if body.detail == "x"
+        ));
+        let result =
+            deserialize_response::<(), String, Problem, SerdeJson>(&response(500, b"oops"));
+        assert!(matches!(result, Err(Error::InvalidStatusCode(500, body)) if body == "oops"));
+    }
+
+    #[test]
+    fn api_error_has_headers() {
+        let mut response = response(429, br#"{"detail":"slow down"}"#);
+        response
+            .headers
+            .insert("x-request-id", HeaderValue::from_static("abc"));
+        let result = deserialize_response::<(), String, Problem, SerdeJson>(&response);
+        assert!(matches!(
+            result,
+            Err(Error::Api { status: 429, headers, .. }) if headers["x-request-id"] == "abc"
+        ));
+    }
+
+    #[test]
+    fn append_query_keeps_existing_query_and_fragment() {
+        let append = |url: &str, query: &str| {
+            let mut url = Url::parse(url).unwrap();
+            append_query(&mut url, query);
+            url.to_string()
+        };
+        assert_eq!(append("http://h/p", "a=1"), "http://h/p?a=1");
+        assert_eq!(append("http://h/p?", "a=1"), "http://h/p?a=1");
+        assert_eq!(append("http://h/p?x=y", "a=1"), "http://h/p?x=y&a=1");
+        assert_eq!(append("http://h/p#frag", "a=1"), "http://h/p?a=1#frag");
+        assert_eq!(
+            append("http://h/p?x=y#f", "a=%20b"),
+            "http://h/p?x=y&a=%20b#f"
+        );
+        assert_eq!(append("http://h/p?x=y", ""), "http://h/p?x=y");
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn msgpack_error_body() {
+        let body = rmp_serde::to_vec_named(&serde_json::json!({ "detail": "x" })).unwrap();
+        let result =
+            deserialize_response::<(), String, Problem, crate::MessagePack>(&response(422, &body));
+        assert!(matches!(
+            result,
+            Err(Error::Api { status: 422, body, .. }) if body.detail == "x"
+        ));
+    }
+
+    struct Fail;
+
+    #[async_trait::async_trait]
+    impl Middleware for Fail {
+        async fn handle(&self, _: HttpRequest, _: Next<'_>) -> Result<HttpResponse, BoxError> {
+            Err("middleware failed".into())
+        }
+    }
+
+    /// Sends the request, then fails with an error of its own.
+    struct FailAfter;
+
+    #[async_trait::async_trait]
+    impl Middleware for FailAfter {
+        async fn handle(
+            &self,
+            request: HttpRequest,
+            next: Next<'_>,
+        ) -> Result<HttpResponse, BoxError> {
+            next.run(request).await?;
+            Err("middleware failed".into())
+        }
+    }
+
+    struct Pass;
+
+    #[async_trait::async_trait]
+    impl Middleware for Pass {
+        async fn handle(
+            &self,
+            request: HttpRequest,
+            next: Next<'_>,
+        ) -> Result<HttpResponse, BoxError> {
+            next.run(request).await
+        }
+    }
+
+    struct FailSign;
+
+    impl Signer for FailSign {
+        fn sign(&self, _: &mut HttpRequest) -> Result<(), BoxError> {
+            Err("signer failed".into())
+        }
+    }
+
+    fn client(transport: Stub) -> Client<All, Stub> {
+        Client::with_inner("http://example.com".into(), transport).unwrap()
+    }
+
+    #[tokio::test]
+    async fn errors_are_reported_where_they_happen() {
+        let refused = || Stub::new(|_| Err("refused".into()));
+        let result = client(Stub::ok()).with(Fail).send(Ping).await;
+        assert!(matches!(result, Err(Error::MiddlewareError(_))));
+        let result = client(Stub::ok()).with(FailAfter).send(Ping).await;
+        assert!(matches!(result, Err(Error::MiddlewareError(_))));
+        let result = client(refused()).send(Ping).await;
+        assert!(matches!(result, Err(Error::TransportError(_))));
+        let result = client(refused()).with(Pass).send(Ping).await;
+        assert!(matches!(result, Err(Error::TransportError(_))));
+        let result = client(Stub::ok())
+            .with(Pass)
+            .signer(FailSign)
+            .send(Ping)
+            .await;
+        assert!(matches!(result, Err(Error::AuthError(_))));
+        let result = client(Stub::ok()).with(Pass).send(Ping).await;
+        assert_eq!(result.unwrap(), "pong");
+    }
+
+    /// `POST /form` with a form body, which is empty if there is no name, and
+    /// the headers.
+    #[derive(Default, serde::Serialize)]
+    struct Form {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<&'static str>,
+        #[serde(skip)]
+        headers: Vec<(&'static str, &'static str)>,
+    }
+
+    impl Request for Form {
+        type Serializer = crate::FormUrlEncoded;
+        type Deserializer = SerdeJson;
+        type Response = serde_json::Value;
+        type ErrorResponse = String;
+
+        fn method(&self) -> HttpMethod {
+            HttpMethod::Post
+        }
+
+        fn path(&self) -> String {
+            "/form".into()
+        }
+
+        fn headers(&self) -> Vec<(String, String)> {
+            self.headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect()
+        }
+    }
+
+    /// The http request that the client sends for the request.
+    async fn sent<Req: Request>(request: Req) -> HttpRequest
+    where
+        Req::ErrorResponse: for<'a> serde::Deserialize<'a>,
+    {
+        let stub = Arc::new(Stub::new(|_| Ok(response(200, b"{}"))));
+        let client = Client::<All, _>::with_inner("http://example.com".into(), stub.clone());
+        client.unwrap().send(request).await.ok();
+        let mut requests = stub.requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        requests.pop().unwrap()
+    }
+
+    fn header<'a>(request: &'a HttpRequest, name: &str) -> Vec<&'a str> {
+        request
+            .headers
+            .get_all(name)
+            .iter()
+            .map(|value| value.to_str().unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn content_type_is_left_out_of_empty_bodies() {
+        let request = sent(Ping).await;
+        assert!(request.body.is_empty());
+        assert!(header(&request, "content-type").is_empty());
+        let request = sent(Form::default()).await;
+        assert!(request.body.is_empty());
+        assert!(header(&request, "content-type").is_empty());
+    }
+
+    #[tokio::test]
+    async fn content_type_comes_from_the_serializer() {
+        let request = sent(Form {
+            name: Some("a b"),
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(request.body, b"name=a+b");
+        assert_eq!(
+            header(&request, "content-type"),
+            ["application/x-www-form-urlencoded"]
+        );
+    }
+
+    #[tokio::test]
+    async fn accept_comes_from_the_deserializer() {
+        assert_eq!(header(&sent(Ping).await, "accept"), ["text/plain"]);
+        let request = sent(Form::default()).await;
+        assert_eq!(header(&request, "accept"), ["application/json"]);
+    }
+
+    #[tokio::test]
+    async fn request_headers_replace_content_type_and_accept() {
+        let request = sent(Form {
+            name: Some("a"),
+            headers: vec![("Content-Type", "text/csv"), ("Accept", "text/csv")],
+        })
+        .await;
+        assert_eq!(header(&request, "content-type"), ["text/csv"]);
+        assert_eq!(header(&request, "accept"), ["text/csv"]);
+    }
+
+    #[tokio::test]
+    async fn request_headers_are_sent() {
+        let request = sent(Form {
+            headers: vec![("X-Trace", "1"), ("X-Tag", "a"), ("x-tag", "b")],
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(header(&request, "x-trace"), ["1"]);
+        assert_eq!(header(&request, "x-tag"), ["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn invalid_request_header_is_an_error() {
+        for (headers, invalid) in [
+            (vec![("bad name", "x")], "bad name"),
+            (
+                vec![("x-ok", "1"), ("x-line", "a\r\nx-injected: 1")],
+                "x-line",
+            ),
+        ] {
+            let stub = Arc::new(Stub::ok());
+            let client = Client::<All, _>::with_inner("http://example.com".into(), stub.clone());
+            let result = client
+                .unwrap()
+                .send(Form {
+                    name: None,
+                    headers,
+                })
+                .await;
+            assert!(
+                matches!(&result, Err(Error::InvalidHeader(name)) if name == invalid),
+                "{result:?}"
+            );
+            assert!(stub.requests.lock().unwrap().is_empty());
+        }
+    }
+
+    #[cfg(feature = "client")]
+    #[tokio::test]
+    async fn builder_settings_are_sent() {
+        crate::request_group!(Pings { Ping });
+
+        let (base_url, server) = crate::testing::serve_once();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-version", HeaderValue::from_static("2"));
+        // the request group is kept by the builder
+        let client: Client<Pings> = Client::builder()
+            .base_url(base_url)
+            .default_headers(headers)
+            .user_agent("my-service/1.0")
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap();
+        assert_eq!(client.send(Ping).await.unwrap(), "pong");
+        let head = server.join().unwrap();
+        assert!(head.starts_with("get /ping http/1.1\r\n"), "{head}");
+        assert!(head.contains("x-api-version: 2\r\n"), "{head}");
+        assert!(head.contains("user-agent: my-service/1.0\r\n"), "{head}");
+    }
+
+    fn join(base: &str, infix: &str, path: &str) -> Result<String, UrlError> {
+        let base = parse_base_url(base)?;
+        join_url(base.as_ref(), infix, path).map(String::from)
+    }
+
+    #[test]
+    fn slashes_between_parts() {
+        let base = "http://example.com";
+        assert_eq!(join(base, "", "users").unwrap(), "http://example.com/users");
+        assert_eq!(
+            join(base, "", "/users").unwrap(),
+            "http://example.com/users"
+        );
+        assert_eq!(
+            join(base, "", "/users/").unwrap(),
+            "http://example.com/users/"
+        );
+        assert_eq!(
+            join(base, "v2", "users").unwrap(),
+            "http://example.com/v2/users"
+        );
+        assert_eq!(
+            join(base, "/v2/", "/users").unwrap(),
+            "http://example.com/v2/users"
+        );
+        assert_eq!(
+            join(base, "v2//", "//users").unwrap(),
+            "http://example.com/v2/users"
+        );
+        assert_eq!(join(base, "v2/", "").unwrap(), "http://example.com/v2/");
+        assert_eq!(join(base, "", "").unwrap(), "http://example.com/");
+    }
+
+    #[test]
+    fn base_with_path() {
+        for base in ["http://example.com/api", "http://example.com/api/"] {
+            assert_eq!(
+                join(base, "", "/users").unwrap(),
+                "http://example.com/api/users"
+            );
+            assert_eq!(
+                join(base, "v2", "users").unwrap(),
+                "http://example.com/api/v2/users"
+            );
+        }
+        assert_eq!(
+            join("http://example.com/api/", "", "").unwrap(),
+            "http://example.com/api/"
+        );
+        assert_eq!(
+            join("http://example.com/api", "", "").unwrap(),
+            "http://example.com/api"
+        );
+    }
+
+    #[test]
+    fn queries_are_combined() {
+        let base = "http://example.com/api?key=abc";
+        assert_eq!(
+            join(base, "v2?beta", "/users?page=2").unwrap(),
+            "http://example.com/api/v2/users?key=abc&beta&page=2"
+        );
+        assert_eq!(
+            join(base, "", "").unwrap(),
+            "http://example.com/api?key=abc"
+        );
+        assert_eq!(
+            join("http://example.com/?", "", "/users?").unwrap(),
+            "http://example.com/users"
+        );
+    }
+
+    #[test]
+    fn fragments() {
+        assert!(parse_base_url("http://example.com/#users").is_err());
+        // only the base url is parsed, so a `#` in the path is a character of
+        // the path
+        assert_eq!(
+            join("http://example.com", "", "/users#1").unwrap(),
+            "http://example.com/users%231"
+        );
+    }
+
+    #[test]
+    fn without_base() {
+        assert_eq!(
+            join("", "http://example.com/api", "users").unwrap(),
+            "http://example.com/api/users"
+        );
+        assert_eq!(
+            join("", "", "http://example.com/users?page=2").unwrap(),
+            "http://example.com/users?page=2"
+        );
+        assert!(join("", "", "/users").is_err());
+    }
+
+    #[test]
+    fn dot_segments_are_rejected() {
+        let base = "http://example.com/api/";
+        for path in [
+            "..",
+            "users/..",
+            "users/../admin",
+            "./users",
+            "users/%2E%2E/admin",
+            "users/%2e./admin",
+            "users/.%2E",
+            "users/%2E",
+            "users\\..\\admin",
+        ] {
+            assert!(join(base, "", path).is_err(), "{path}");
+            assert!(join(base, path, "/users").is_err(), "{path}");
         }
+        assert_eq!(
+            join(base, "", "/a.b/.../..c?x=..").unwrap(),
+            "http://example.com/api/a.b/.../..c?x=.."
+        );
+        let path: String = crate::Path::new("/users").segment("..").into();
+        assert!(join(base, "", &path).is_err());
     }
 }
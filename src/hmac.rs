@@ -0,0 +1,443 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ring::{digest, hmac};
+
+use crate::hex;
+
+#[cfg(feature = "client-core")]
+use crate::middleware::{BoxError, HttpRequest, Signer};
+
+/// Signs requests with HMAC-SHA256. The signature is computed over this
+/// string, where the timestamp is the number of seconds since the unix epoch,
+/// and the body hash is the hex encoded SHA-256 hash of the request body:
+///
+/// ```text
+/// {method}\n{path and query}\n{timestamp}\n{body hash}
+/// ```
+///
+/// The hex encoded signature and the timestamp are sent in the `X-Signature`
+/// and `X-Timestamp` headers by default. Servers can check the signature with
+/// the `HmacVerifier` returned by `verifier`.
+///
+/// ```ignore
+/// let signer = HmacSigner::new(b"secret").key_id("partner-1");
+/// let client = Client::<MyApi>::new("http://example.com".into())?.signer(signer);
+/// ```
+#[derive(Clone)]
+pub struct HmacSigner {
+    key: hmac::Key,
+    key_id: Option<String>,
+    signature_header: String,
+    timestamp_header: String,
+    key_id_header: String,
+}
+
+/// Checks the signatures of requests signed by an `HmacSigner` with the same
+/// configuration.
+///
+/// ```ignore
+/// let verifier = HmacSigner::new(b"secret").key_id("partner-1").verifier();
+/// verifier.verify(
+///     GetUser::METHOD.as_str(),
+///     "/api/users/123",
+///     |name| headers.get(name)?.to_str().ok().map(Into::into),
+///     &body,
+/// )?;
+/// ```
+///
+/// With the `axum` feature, `verify_parts` checks a request received by axum,
+/// for example in a middleware in front of a `Routes` router:
+///
+/// ```ignore
+/// let app = Router::new()
+///     .nest("/api", Routes::<MyApi>::new().handle(get_user).into_router())
+///     .layer(axum::middleware::from_fn(move |request: Request, next: Next| {
+///         let verifier = verifier.clone();
+///         async move {
+///             let (parts, body) = request.into_parts();
+///             let body = axum::body::to_bytes(body, 1 << 20).await.map_err(|_| StatusCode::BAD_REQUEST)?;
+///             verifier.verify_parts(&parts, &body).map_err(|_| StatusCode::UNAUTHORIZED)?;
+///             Ok::<_, StatusCode>(next.run(Request::from_parts(parts, body.into())).await)
+///         }
+///     }));
+/// ```
+#[derive(Debug, Clone)]
+pub struct HmacVerifier {
+    signer: HmacSigner,
+    max_age: Duration,
+}
+
+/// The reason a request could not be verified by `HmacVerifier`.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum SignatureError {
+    #[error("missing header: {0}")]
+    MissingHeader(String),
+    #[error("invalid timestamp: {0}")]
+    InvalidTimestamp(String),
+    #[error("timestamp is more than {0:?} from the current time")]
+    Expired(Duration),
+    #[error("unexpected key id: {0}")]
+    UnknownKeyId(String),
+    #[error("invalid signature")]
+    InvalidSignature,
+}
+
+impl HmacSigner {
+    pub fn new(key: impl AsRef<[u8]>) -> Self {
+        Self {
+            key: hmac::Key::new(hmac::HMAC_SHA256, key.as_ref()),
+            key_id: None,
+            signature_header: "X-Signature".to_owned(),
+            timestamp_header: "X-Timestamp".to_owned(),
+            key_id_header: "X-Key-Id".to_owned(),
+        }
+    }
+
+    /// Identifies the key to the server, sent in the `X-Key-Id` header.
+    pub fn key_id(mut self, key_id: impl Into<String>) -> Self {
+        self.key_id = Some(key_id.into());
+        self
+    }
+
+    /// Name of the header for the signature. Defaults to `X-Signature`.
+    pub fn signature_header(mut self, name: impl Into<String>) -> Self {
+        self.signature_header = name.into();
+        self
+    }
+
+    /// Name of the header for the timestamp. Defaults to `X-Timestamp`.
+    pub fn timestamp_header(mut self, name: impl Into<String>) -> Self {
+        self.timestamp_header = name.into();
+        self
+    }
+
+    /// Name of the header for the key id. Defaults to `X-Key-Id`.
+    pub fn key_id_header(mut self, name: impl Into<String>) -> Self {
+        self.key_id_header = name.into();
+        self
+    }
+
+    /// A verifier for requests signed by this signer, which accepts
+    /// timestamps up to 5 minutes from the current time.
+    pub fn verifier(&self) -> HmacVerifier {
+        HmacVerifier {
+            signer: self.clone(),
+            max_age: Duration::from_secs(300),
+        }
+    }
+
+    /// The headers to add to a request, as (name, value) pairs.
+    pub fn headers(
+        &self,
+        method: &str,
+        path_and_query: &str,
+        body: &[u8],
+    ) -> Vec<(String, String)> {
+        let timestamp = unix_time().to_string();
+        let signature = hmac::sign(
+            &self.key,
+            string_to_sign(method, path_and_query, &timestamp, body).as_bytes(),
+        );
+        let mut headers = vec![
+            (self.signature_header.clone(), hex(signature.as_ref())),
+            (self.timestamp_header.clone(), timestamp),
+        ];
+        if let Some(key_id) = &self.key_id {
+            headers.push((self.key_id_header.clone(), key_id.clone()));
+        }
+        headers
+    }
+}
+
+/// Does not show the key.
+impl std::fmt::Debug for HmacSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HmacSigner")
+            .field("key_id", &self.key_id)
+            .field("signature_header", &self.signature_header)
+            .field("timestamp_header", &self.timestamp_header)
+            .field("key_id_header", &self.key_id_header)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "client-core")]
+impl Signer for HmacSigner {
+    fn sign(&self, request: &mut HttpRequest) -> Result<(), BoxError> {
+        let url = url::Url::parse(&request.url)?;
+        let path_and_query = &url[url::Position::BeforePath..url::Position::AfterQuery];
+        let headers = self.headers(request.method.as_str(), path_and_query, &request.body);
+        for (name, value) in headers {
+            request.headers.insert(
+                http::HeaderName::from_bytes(name.as_bytes())?,
+                value.parse()?,
+            );
+        }
+        Ok(())
+    }
+}
+
+impl HmacVerifier {
+    /// How far the timestamp may be from the current time, in either
+    /// direction. Defaults to 5 minutes.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Check the signature of a request. `header` returns the value of the
+    /// header with the given name, if it is present.
+    ///
+    /// The client signs the path and query of the full url that it sends the
+    /// request to, including the path of its base url, such as
+    /// `/api/users/123?full=true`. Pass the path as it was received, not the
+    /// path that a nested router matches, or `Req::path()`, which do not
+    /// include the prefix.
+    pub fn verify(
+        &self,
+        method: &str,
+        path_and_query: &str,
+        header: impl Fn(&str) -> Option<String>,
+        body: &[u8],
+    ) -> Result<(), SignatureError> {
+        let signer = &self.signer;
+        let required =
+            |name: &String| header(name).ok_or_else(|| SignatureError::MissingHeader(name.clone()));
+        if let Some(key_id) = &signer.key_id {
+            let received = required(&signer.key_id_header)?;
+            if &received != key_id {
+                return Err(SignatureError::UnknownKeyId(received));
+            }
+        }
+        let timestamp = required(&signer.timestamp_header)?;
+        let seconds: u64 = timestamp
+            .parse()
+            .map_err(|_| SignatureError::InvalidTimestamp(timestamp.clone()))?;
+        if unix_time().abs_diff(seconds) > self.max_age.as_secs() {
+            return Err(SignatureError::Expired(self.max_age));
+        }
+        let signature = required(&signer.signature_header)?;
+        let signature = unhex(&signature).ok_or(SignatureError::InvalidSignature)?;
+        hmac::verify(
+            &signer.key,
+            string_to_sign(method, path_and_query, &timestamp, body).as_bytes(),
+            &signature,
+        )
+        .map_err(|_| SignatureError::InvalidSignature)
+    }
+}
+
+#[cfg(feature = "axum")]
+impl HmacVerifier {
+    /// Check the signature of a request received by axum. The path is read
+    /// from the `OriginalUri` extension if it is present, which axum sets to
+    /// the full path of the request, so it is the path that the client signed
+    /// even inside a nested router.
+    pub fn verify_parts(
+        &self,
+        parts: &axum::http::request::Parts,
+        body: &[u8],
+    ) -> Result<(), SignatureError> {
+        let uri = match parts.extensions.get::<axum::extract::OriginalUri>() {
+            Some(original) => &original.0,
+            None => &parts.uri,
+        };
+        let path_and_query = uri.path_and_query().map_or("/", |path| path.as_str());
+        self.verify(
+            parts.method.as_str(),
+            path_and_query,
+            |name| parts.headers.get(name)?.to_str().ok().map(Into::into),
+            body,
+        )
+    }
+}
+
+fn string_to_sign(method: &str, path_and_query: &str, timestamp: &str, body: &[u8]) -> String {
+    let body_hash = digest::digest(&digest::SHA256, body);
+    format!(
+        "{method}\n{path_and_query}\n{timestamp}\n{}",
+        hex(body_hash.as_ref())
+    )
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Decode hex, returning None if the length is odd or a character is not a
+/// hex digit.
+fn unhex(hex: &str) -> Option<Vec<u8>> {
+    let digit = |byte: u8| (byte as char).to_digit(16);
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [high, low] => Some((digit(*high)? * 16 + digit(*low)?) as u8),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATH: &str = "/api/users/1?full=true";
+    const BODY: &[u8] = br#"{"name":"a"}"#;
+
+    fn signer() -> HmacSigner {
+        HmacSigner::new(b"secret").key_id("partner-1")
+    }
+
+    fn verify(
+        verifier: &HmacVerifier,
+        path: &str,
+        headers: &[(String, String)],
+        body: &[u8],
+    ) -> Result<(), SignatureError> {
+        verifier.verify(
+            "POST",
+            path,
+            |name| {
+                let (_, value) = headers.iter().find(|(header, _)| header == name)?;
+                Some(value.clone())
+            },
+            body,
+        )
+    }
+
+    /// The headers of a request signed `age` seconds ago, or in the future if
+    /// it is negative.
+    fn signed_ago(signer: &HmacSigner, age: i64) -> Vec<(String, String)> {
+        let timestamp = unix_time().saturating_add_signed(-age).to_string();
+        let signature = hmac::sign(
+            &signer.key,
+            string_to_sign("POST", PATH, &timestamp, BODY).as_bytes(),
+        );
+        vec![
+            ("X-Signature".into(), hex(signature.as_ref())),
+            ("X-Timestamp".into(), timestamp),
+            ("X-Key-Id".into(), "partner-1".into()),
+        ]
+    }
+
+    fn set(headers: &mut [(String, String)], name: &str, value: &str) {
+        headers
+            .iter_mut()
+            .find(|(header, _)| header == name)
+            .unwrap()
+            .1 = value.into();
+    }
+
+    #[test]
+    fn round_trip() {
+        let verifier = signer().verifier();
+        let headers = signer().headers("POST", PATH, BODY);
+        assert_eq!(verify(&verifier, PATH, &headers, BODY), Ok(()));
+        assert_eq!(
+            verify(&verifier, PATH, &signed_ago(&signer(), 299), BODY),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn tampered_request() {
+        let verifier = signer().verifier();
+        let headers = signer().headers("POST", PATH, BODY);
+        let tampered = br#"{"name":"b"}"#;
+        assert_eq!(
+            verify(&verifier, PATH, &headers, tampered),
+            Err(SignatureError::InvalidSignature)
+        );
+        // the path that a nested router sees
+        assert_eq!(
+            verify(&verifier, "/users/1?full=true", &headers, BODY),
+            Err(SignatureError::InvalidSignature)
+        );
+        let result = verifier.verify("PUT", PATH, |_| None, BODY);
+        assert_eq!(
+            result,
+            Err(SignatureError::MissingHeader("X-Key-Id".into()))
+        );
+        let other_key = HmacSigner::new(b"other").key_id("partner-1");
+        let headers = other_key.headers("POST", PATH, BODY);
+        assert_eq!(
+            verify(&verifier, PATH, &headers, BODY),
+            Err(SignatureError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn expired_timestamp() {
+        let verifier = signer().verifier();
+        for age in [301, -301] {
+            assert_eq!(
+                verify(&verifier, PATH, &signed_ago(&signer(), age), BODY),
+                Err(SignatureError::Expired(Duration::from_secs(300)))
+            );
+        }
+        let verifier = verifier.max_age(Duration::from_secs(10));
+        assert_eq!(
+            verify(&verifier, PATH, &signed_ago(&signer(), 20), BODY),
+            Err(SignatureError::Expired(Duration::from_secs(10)))
+        );
+        let mut headers = signed_ago(&signer(), 0);
+        set(&mut headers, "X-Timestamp", "soon");
+        assert_eq!(
+            verify(&verifier, PATH, &headers, BODY),
+            Err(SignatureError::InvalidTimestamp("soon".into()))
+        );
+    }
+
+    #[test]
+    fn wrong_key_id() {
+        let verifier = signer().verifier();
+        let headers = HmacSigner::new(b"secret")
+            .key_id("partner-2")
+            .headers("POST", PATH, BODY);
+        assert_eq!(
+            verify(&verifier, PATH, &headers, BODY),
+            Err(SignatureError::UnknownKeyId("partner-2".into()))
+        );
+    }
+
+    #[test]
+    fn malformed_signature() {
+        let verifier = signer().verifier();
+        let mut headers = signer().headers("POST", PATH, BODY);
+        let signature = headers[0].1.clone();
+        for malformed in [&signature[1..], &format!("+{}", &signature[1..]), ""] {
+            set(&mut headers, "X-Signature", malformed);
+            assert_eq!(
+                verify(&verifier, PATH, &headers, BODY),
+                Err(SignatureError::InvalidSignature)
+            );
+        }
+        assert_eq!(unhex("0aFf"), Some(vec![0x0a, 0xff]));
+        assert_eq!(unhex("abc"), None);
+        assert_eq!(unhex("+f"), None);
+        assert_eq!(unhex("zz"), None);
+    }
+
+    #[cfg(feature = "axum")]
+    #[test]
+    fn verify_parts_uses_the_original_uri() {
+        use axum::{extract::OriginalUri, http::Request};
+
+        let verifier = signer().verifier();
+        let mut request = Request::post("/users/1?full=true");
+        for (name, value) in signer().headers("POST", PATH, BODY) {
+            request = request.header(name, value);
+        }
+        let (parts, ()) = request.body(()).unwrap().into_parts();
+        assert_eq!(
+            verifier.verify_parts(&parts, BODY),
+            Err(SignatureError::InvalidSignature)
+        );
+        let mut nested = parts.clone();
+        nested.extensions.insert(OriginalUri(PATH.parse().unwrap()));
+        assert_eq!(verifier.verify_parts(&nested, BODY), Ok(()));
+    }
+}
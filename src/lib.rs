@@ -8,8 +8,9 @@
 //! will work for you:
 //! 1. request-response communication
 //! 2. async rust functions
-//! 3. communicate over http (uses reqwest under the hood)
-//! 4. http body is serialized as json
+//! 3. communicate over http (uses reqwest by default)
+//! 4. http body is serialized as json (or another format with a custom
+//!    serializer or deserializer)
 //! 5. status codes outside the 200 range are considered errors
 //! 6. request and response types must be serializable and deserializable using
 //!    serde
@@ -28,8 +29,13 @@
 //! simply invoke `Client::send` to send requests.
 //!
 //! ```rust
-//! let client = Client::new("http://example.com");
+//! # use http_typed::*;
+//! # http_typed::__doc_requests!(MyRequest);
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = Client::<All>::new("http://example.com".into())?;
 //! let response = client.send(MyRequest::new()).await?;
+//! # Ok(())
+//! # }
 //! ```
 //!
 //! ### Basic
@@ -38,12 +44,17 @@
 //! and basic way to use this library is by using `send_custom`.
 //!
 //! ```rust
+//! # use http_typed::*;
+//! # http_typed::__doc_requests!(MyRequest);
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
 //! let my_response: MyResponse = send_custom(
 //!     "http://example.com/path/to/my/request/",
 //!     HttpMethod::Get,
 //!     MyRequest::new()
 //! )
 //! .await?;
+//! # Ok(())
+//! # }
 //! ```
 //!
 //! ### Client
@@ -54,15 +65,378 @@
 //! *method* instead to re-use an existing client for every request.
 //!
 //! ```rust
-//! let client = Client::default();
+//! # use http_typed::*;
+//! # http_typed::__doc_requests!(MyRequest);
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = Client::<All>::default();
 //! let my_response: MyResponse = client.send_custom(
 //!     "http://example.com/path/to/my/request/",
 //!     HttpMethod::Get,
 //!     MyRequest::new()
 //! )
 //! .await?;
+//! # Ok(())
+//! # }
 //! ```
 //!
+//! ### Client configuration
+//!
+//! Use `Client::builder` to set default headers, timeouts, a user agent, or
+//! connection pool settings that apply to every request sent by the client.
+//!
+//! ```ignore
+//! let client = Client::<MyApi>::builder()
+//!     .base_url("http://example.com")
+//!     .timeout(Duration::from_secs(10))
+//!     .user_agent("my-service/1.0")
+//!     .build()?;
+//! ```
+//!
+//! If you already have a configured `reqwest::Client`, you can use it directly
+//! with `Client::with_inner`.
+//!
+//! ### Middleware
+//!
+//! Middleware runs around every request sent by a `Client`, and can be used
+//! for logging, metrics, authentication, or propagating headers. It has
+//! access to the serialized request, including the method, url, headers and
+//! body, and to the raw response before it is deserialized.
+//!
+//! ```ignore
+//! struct Log;
+//!
+//! #[async_trait::async_trait]
+//! impl Middleware for Log {
+//!     async fn handle(&self, request: HttpRequest, next: Next<'_>) -> Result<HttpResponse, BoxError> {
+//!         println!("{:?} {}", request.method, request.url);
+//!         let response = next.run(request).await?;
+//!         println!("{}", response.status);
+//!         Ok(response)
+//!     }
+//! }
+//!
+//! let client = Client::<MyApi>::new("http://example.com".into())?.with(Log);
+//! ```
+//!
+//! Errors returned by middleware are surfaced as `Error::MiddlewareError`.
+//!
+//! ### Retries
+//!
+//! A `Client` can retry requests that fail with a transient error: connection
+//! failures, timeouts, and responses with status 429, 502, 503 or 504. Retries
+//! use exponential backoff with jitter, and honor the `Retry-After` header up
+//! to `max_retry_after` (60s by default). A response asking for a longer wait
+//! is returned without retrying.
+//!
+//! ```ignore
+//! let client = Client::<MyApi>::new("http://example.com".into())?.retry(
+//!     RetryPolicy::new()
+//!         .max_attempts(5)
+//!         .max_elapsed(Duration::from_secs(30))
+//!         .on_retry(|event| println!("retrying after attempt {}", event.attempt)),
+//! );
+//! ```
+//!
+//! Only idempotent requests are retried, based on their http method. A request
+//! with a non-idempotent method like POST can opt into retries by overriding
+//! `Request::idempotent` to return true.
+//!
+//! ### Authentication
+//!
+//! A `Client` can add credentials to every request it sends. `Auth` supports
+//! bearer tokens, basic auth, and api keys sent in a header or in the query
+//! string.
+//!
+//! ```ignore
+//! let client = Client::<MyApi>::new("http://example.com".into())?
+//!     .auth(Auth::basic("user", Some("password")));
+//!
+//! let client = Client::<MyApi>::builder()
+//!     .base_url("http://example.com")
+//!     .auth(Auth::api_key_header("X-Api-Key", "my-key"))
+//!     .build()?;
+//! ```
+//!
+//! Tokens that change over time can be provided by implementing
+//! `TokenProvider`, which is asked for a bearer token before each request is
+//! sent.
+//!
+//! ```ignore
+//! let client = Client::<MyApi>::new("http://example.com".into())?
+//!     .auth(Auth::provider(MyTokenProvider::new()));
+//! ```
+//!
+//! A request can opt out of authentication by overriding
+//! `Request::authenticate` to return false.
+//!
+//! ### OAuth2
+//!
+//! With the `oauth2` feature, `ClientCredentials` provides tokens using the
+//! OAuth2 client credentials grant. Tokens are fetched from the token endpoint
+//! when they are first needed, and cached until shortly before they expire.
+//! Concurrent requests share a single token refresh, and a request that is
+//! rejected with status 401 is sent once more with a new token.
+//!
+//! ```ignore
+//! let provider = ClientCredentials::new("https://auth.example.com/token", "client-id", "client-secret")?
+//!     .scope("read write");
+//! let client = Client::<MyApi>::new("https://api.example.com".into())?
+//!     .auth(Auth::provider(provider));
+//! ```
+//!
+//! The token endpoint is called with `ClientCredentialsRequest`, which
+//! implements `Request`, so its errors are returned as `Error::Api` with a
+//! `TokenErrorResponse`.
+//!
+//! ### Signing
+//!
+//! A `Signer` can add a signature to every request sent by a `Client`. It runs
+//! after the middleware, immediately before the request is sent, so the
+//! signature covers the method, url, headers and body exactly as they are sent.
+//!
+//! With the `hmac` feature, `HmacSigner` signs the method, path, a timestamp,
+//! and a hash of the body with HMAC-SHA256, and sends the signature in a
+//! header. The header names are configurable.
+//!
+//! ```ignore
+//! let signer = HmacSigner::new(b"secret").key_id("partner-1");
+//! let client = Client::<MyApi>::new("http://example.com".into())?.signer(signer.clone());
+//! ```
+//!
+//! Servers can check the signature of the same requests with an `HmacVerifier`,
+//! which also rejects requests with a timestamp more than 5 minutes from the
+//! current time.
+//!
+//! ```ignore
+//! let verifier = signer.verifier();
+//! verifier.verify(
+//!     GetUser::METHOD.as_str(),
+//!     "/users/123",
+//!     |name| headers.get(name)?.to_str().ok().map(Into::into),
+//!     &body,
+//! )?;
+//! ```
+//!
+//! The path is the one the client sent the request to, including the path of
+//! its base url, so inside a nested axum router it is not the path that the
+//! router matched. With the `axum` feature, `HmacVerifier::verify_parts` reads
+//! the full path from axum's `OriginalUri`, for use in a middleware.
+//!
+//! ### AWS Signature Version 4
+//!
+//! With the `sigv4` feature, `SigV4Signer` signs requests for API Gateway, S3,
+//! and other services that accept AWS Signature Version 4. The signature
+//! includes a hash of the serialized body, and the query string is
+//! canonicalized before the request is sent.
+//!
+//! ```ignore
+//! let credentials = AwsCredentials::new("access-key-id", "secret-access-key");
+//! let client = Client::<MyApi>::new("http://localhost:9000".into())?
+//!     .signer(SigV4Signer::new("us-east-1", "s3", credentials));
+//! ```
+//!
+//! ### Response metadata
+//!
+//! `Client::send` only returns the deserialized response. If you also need the
+//! status code, headers, final url, or timing of the response, use
+//! `Client::send_with_meta` (or `Client::send_to_with_meta`), which returns a
+//! `Response` containing the deserialized value along with that metadata.
+//!
+//! ```ignore
+//! let response = client.send_with_meta(ListUsers::new()).await?;
+//! let next_page = response.headers.get("Link");
+//! let users = response.value;
+//! ```
+//!
+//! ### Transports
+//!
+//! By default, a `Client` sends requests using reqwest. To use a different http
+//! library, implement the `Transport` trait and pass it to
+//! `Client::with_inner`. The transport receives the fully serialized request,
+//! so the same `Request` types, middleware, and retries work with any
+//! transport.
+//!
+//! ```ignore
+//! struct MyTransport(my_http::Client);
+//!
+//! #[async_trait::async_trait]
+//! impl Transport for MyTransport {
+//!     async fn send(&self, request: HttpRequest) -> Result<HttpResponse, BoxError> {
+//!         // ...
+//!     }
+//! }
+//!
+//! let client = Client::<MyApi, _>::with_inner("http://example.com".into(), MyTransport::new());
+//! ```
+//!
+//! To use a custom transport without depending on reqwest at all, disable the
+//! default features and enable `client-core` instead of `client`:
+//!
+//! ```toml
+//! http-typed = { version = "0.5", default-features = false, features = ["client-core"] }
+//! ```
+//!
+//! Errors returned by a custom transport are surfaced as
+//! `Error::TransportError`.
+//!
+//! ### Mocking
+//!
+//! With the `mock` feature, `MockClient` can stand in for a `Client` in unit
+//! tests, so they do not need an http server. Register a response for each
+//! request type with `on`, and inspect the requests that were sent afterwards.
+//! Handlers return the same `Error` type as the real client, so error handling
+//! can be tested too. Only requests in the `MockClient`'s request group can be
+//! registered or sent, and sending a request type without a handler returns
+//! `Error::InvalidStatusCode` with status 404. Responses for `send_custom` are
+//! registered with `on_custom`, for each request and response type.
+//!
+//! ```ignore
+//! #[cfg(not(test))]
+//! type ApiClient = Client<MyApi>;
+//! #[cfg(test)]
+//! type ApiClient = MockClient<MyApi>;
+//!
+//! #[tokio::test]
+//! async fn test_rename() {
+//!     let client = ApiClient::default();
+//!     client.on::<GetUser>(|request| Ok(User { id: request.id.clone(), name: "old".into() }));
+//!     client.on::<UpdateUser>(|_| Err(Error::Api { status: 409, headers: Default::default(), body: "conflict".into() }));
+//!
+//!     assert!(rename_user(&client, "123", "new").await.is_err());
+//!     assert_eq!(client.call_count::<UpdateUser>(), 1);
+//!     assert_eq!(client.requests::<UpdateUser>()[0].name, "new");
+//! }
+//! ```
+//!
+//! ### Record and replay
+//!
+//! With the `cassette` feature, the `Cassette` middleware records the requests
+//! sent by a `Client` and their responses to a json file, and can later replay
+//! them without network access, so integration tests can run offline. Matching
+//! on the query, body, and headers is configurable, and secrets in headers and
+//! query parameters can be redacted before they are recorded. Recordings are
+//! kept in memory until `save` writes them to the file.
+//!
+//! ```ignore
+//! let cassette = if std::env::var("RECORD").is_ok() {
+//!     Cassette::record("tests/cassettes/users.json")
+//! } else {
+//!     Cassette::replay("tests/cassettes/users.json")?
+//! };
+//! let cassette = cassette
+//!     .redact_header("authorization")
+//!     .redact_query("api_key")
+//!     .ignore_header("x-request-id")
+//!     .match_headers(true);
+//!
+//! let client = Client::<MyApi>::new("http://example.com".into())?.with(cassette.clone());
+//! run_tests(&client).await;
+//! if cassette.mode() == CassetteMode::Record {
+//!     cassette.save()?;
+//! }
+//! ```
+//!
+//! ### Blocking
+//!
+//! With the `blocking` feature, `blocking::Client` provides the same typed API
+//! as `Client` for synchronous code, such as command line tools and build
+//! scripts. It supports request groups, `send_to`, `send_custom`,
+//! `send_with_meta`, `Auth` and `Signer`, and uses reqwest's blocking client.
+//! Middleware, retries, token providers and custom transports are only
+//! available in the async `Client`.
+//!
+//! ```ignore
+//! let client = blocking::Client::<MyApi>::new("http://example.com".into())?;
+//! let user = client.send(GetUser { id: "123".into() })?;
+//!
+//! let my_response: MyResponse = blocking::send_custom(
+//!     "http://example.com/path/to/my/request/",
+//!     HttpMethod::Get,
+//!     MyRequest::new(),
+//! )?;
+//! ```
+//!
+//! ### Server
+//!
+//! The same `Request` types can be used to implement the server. With the
+//! `axum` feature, `Routes` builds an axum `Router` from the requests in a
+//! request group. Each request is routed by the method and path template from
+//! its `Route` implementation, which `#[derive(Request)]` generates from the
+//! same attribute as `Request`, so the client and server always agree on them.
+//! The request is parsed with the reverse of its `Serializer`, and the
+//! handler's response is written with the reverse of its `Deserializer`. Return
+//! an `ApiError` from a handler to respond with an `ErrorResponse`.
+//!
+//! ```ignore
+//! let app: axum::Router = Routes::<MyApi>::new()
+//!     .handle(|request: GetUser| async move {
+//!         Ok(User { id: request.id, name: "name".into() })
+//!     })
+//!     .handle(|request: CreateUser| async move {
+//!         Err(ApiError::new(409, "user already exists".to_string()))
+//!     })
+//!     .into();
+//! ```
+//!
+//! To use another server framework, enable the `server` feature, and use
+//! `Route`, `DeserializeRequest`, and `SerializeResponse` directly.
+//!
+//! ### OpenAPI
+//!
+//! With the `openapi` feature, `OpenApi` generates an OpenAPI 3.0 document from
+//! the requests in a request group, so the spec is always in sync with the
+//! code. Each request is described using its `Route` implementation for the
+//! method and path, and `schemars` for the schemas of the request, the
+//! response, and the error response. All of these types must implement
+//! `schemars::JsonSchema`.
+//!
+//! ```ignore
+//! request_group!(MyApi { GetUser, CreateUser });
+//!
+//! let document = OpenApi::new("My API", "1.0.0")
+//!     .server("https://api.example.com")
+//!     .group::<MyApi>()
+//!     .to_json();
+//! std::fs::write("openapi.json", serde_json::to_string_pretty(&document)?)?;
+//! ```
+//!
+//! `group` only describes the requests listed in `request_group!`. Requests
+//! added to a group with `#[request(group = ...)]` are not listed, so describe
+//! them individually with `OpenApi::request::<GetUser>()`. A group without any
+//! listed requests does not compile.
+//!
+//! ### Code generation
+//!
+//! For APIs described by an OpenAPI 3 document, the `http-typed-codegen` crate
+//! generates the request and response types, their `Request` implementations,
+//! and a request group containing every request. It can be used from a build
+//! script:
+//!
+//! ```ignore
+//! // build.rs
+//! fn main() {
+//!     let out_dir = std::env::var("OUT_DIR").unwrap();
+//!     http_typed_codegen::Generator::new()
+//!         .group("Petstore")
+//!         .generate_file("petstore.yaml", format!("{out_dir}/petstore.rs"))
+//!         .unwrap();
+//! }
+//!
+//! // lib.rs
+//! include!(concat!(env!("OUT_DIR"), "/petstore.rs"));
+//! ```
+//!
+//! Or from the command line, to generate code that is checked in:
+//!
+//! ```text
+//! http-typed-codegen petstore.yaml --group Petstore --output src/petstore.rs
+//! ```
+//!
+//! Request types derive `Serialize`. For a server, `Generator::server` (or
+//! `--server`) also derives `Deserialize` for the requests that `Routes` can
+//! rebuild, and `Generator::json_schema` (or `--json-schema`) derives
+//! `schemars::JsonSchema` for `OpenApi`.
+//!
 //! ### Request
 //!
 //! You may also prefer not to specify metadata about the request every time you
@@ -71,10 +445,17 @@
 //! implementing the Request trait.
 //!
 //! ```rust
-//! pub trait Request {
+//! # use http_typed::{DeserializeBody, HttpMethod, SerializeBody};
+//! pub trait Request: Sized {
+//!     type Serializer: SerializeBody<Self>;
+//!     type Deserializer: DeserializeBody<Self::Response>;
 //!     type Response;
+//!     type ErrorResponse;
 //!     fn method(&self) -> HttpMethod;
 //!     fn path(&self) -> String;
+//!     fn headers(&self) -> Vec<(String, String)> { vec![] }
+//!     fn idempotent(&self) -> bool { self.method().is_idempotent() }
+//!     fn authenticate(&self) -> bool { true }
 //! }
 //! ```
 //!
@@ -92,20 +473,214 @@
 //! performance is not optimal if you are sending multiple requests.
 //!
 //! ```rust
+//! # use http_typed::*;
+//! # http_typed::__doc_requests!(MyRequest);
+//! # async fn example() -> Result<String, Box<dyn std::error::Error>> {
 //! let my_response = send("http://example.com", MyRequest::new()).await?;
 //! // The type of my_response is determined by the trait's associated type.
 //! // It does not need to be inferrable from the calling context.
-//! return my_response.some_field
+//! return Ok(my_response.some_field)
+//! # }
 //! ```
 //!
 //! If you want to send multiple requests, or if you don't want to include the
 //! base url when calling `send`, instantiate a Client:
 //!
 //! ```rust
-//! let client = Client::new("http://example.com");
+//! # use http_typed::*;
+//! # http_typed::__doc_requests!(MyRequest);
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = Client::<All>::new("http://example.com".into())?;
 //! let my_response = client.send(MyRequest::new()).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ### Paths
+//!
+//! Values included in the path of a request, such as ids, must be
+//! percent-encoded, otherwise an id containing `/` or a space produces the
+//! wrong url. `Path` builds a path from segments and encodes each of them, and
+//! can be converted into the String returned by `Request::path`.
+//!
+//! ```ignore
+//! fn path(&self) -> String {
+//!     // "/users/a%2Fb/posts" for the id "a/b"
+//!     Path::new("/users").segment(&self.id).segment("posts").into()
+//! }
+//! ```
+//!
+//! ### Derive
+//!
+//! With the `derive` feature, `Request` can be derived instead of implemented
+//! by hand. The path template interpolates fields of the struct, and their
+//! values are percent-encoded.
+//!
+//! ```ignore
+//! #[derive(Serialize, Request)]
+//! #[request(method = "GET", path = "/users/{id}", response = User, body = NoBody)]
+//! pub struct GetUser {
+//!     id: String,
+//! }
+//! ```
+//!
+//! The supported attributes are:
+//! - `method`: http method, such as "GET" or "POST" (required)
+//! - `path`: path template with `{field}` parameters (required)
+//! - `response`: the `Response` type (required)
+//! - `error`: the `ErrorResponse` type, defaults to `String`
+//! - `body`: the `Serializer`, defaults to `SerdeJson`
+//! - `deserializer`: the `Deserializer`, defaults to `SerdeJson`
+//! - `group`: a request group to add the request to, may be repeated
+//!
+//! When using `group`, define the group without any requests, and derive will
+//! add each request to it:
+//!
+//! ```ignore
+//! request_group!(pub MyApi {});
+//!
+//! #[derive(Serialize, Request)]
+//! #[request(method = "POST", path = "/users", response = User, group = MyApi)]
+//! pub struct CreateUser {
+//!     name: String,
+//! }
+//! ```
+//!
+//! Requests added this way can be sent by a `Client<MyApi>`, but they are not
+//! listed in the group, so `OpenApi::group` does not see them. List the
+//! requests in `request_group!` instead when the group is used with `OpenApi`.
+//!
+//! ### Headers
+//!
+//! Headers that are part of the request's definition can be specified by
+//! overriding `Request::headers`. They are sent with every request of that
+//! type, and replace any default headers with the same name.
+//!
+//! ```ignore
+//! impl Request for UpdateUser {
+//!     // ...
+//!     fn headers(&self) -> Vec<(String, String)> {
+//!         vec![("If-Match".into(), self.etag.clone())]
+//!     }
+//! }
+//! ```
+//!
+//! ### Body formats
+//!
+//! The `Serializer` and `Deserializer` associated types select how the request
+//! body is encoded and how the response body is decoded. The following are
+//! provided, and you can implement `SerializeBody` and `DeserializeBody` to
+//! support any other format:
+//!
+//! - `SerdeJson`: json using serde (request and response)
+//! - `SerdeQuery`: url query string using serde (request only)
+//! - `FormUrlEncoded`: `application/x-www-form-urlencoded` body using serde,
+//!   like an html form (request only)
+//! - `Multipart`: `multipart/form-data` body with text fields and files, from
+//!   `ToMultipart` (request only)
+//! - `NoBody`: empty request body, or ignore the response body
+//! - `PlainText`: response body as a `String`
+//! - `RawBytes`: response body as a `Vec<u8>`
+//! - `MessagePack`: MessagePack using serde, requires the `msgpack` feature
+//!
+//! ```ignore
+//! impl Request for DownloadReport {
+//!     type Serializer = NoBody;
+//!     type Deserializer = PlainText;
+//!     type Response = String;
+//!     type ErrorResponse = String;
+//!     fn method(&self) -> HttpMethod {
+//!         HttpMethod::Get
+//!     }
+//!     fn path(&self) -> String {
+//!         format!("/reports/{}.csv", self.id)
+//!     }
+//! }
+//! ```
+//!
+//! The serializer determines the Content-Type header of the request, which is
+//! left out when the body is empty, and the deserializer determines the Accept
+//! header, such as `application/json` for `SerdeJson`. `Request::headers` can
+//! replace either of them.
+//!
+//! ### Query strings
+//!
+//! Requests that carry their data in the url, such as typical GET requests
+//! for list and search endpoints, can use `SerdeQuery` as their `Serializer`.
+//! The request is serialized with serde into a percent-encoded query string,
+//! and the body is left empty.
+//!
+//! ```ignore
+//! #[derive(Serialize)]
+//! struct SearchUsers {
+//!     name: String,
+//!     limit: u32,
+//! }
+//!
+//! impl Request for SearchUsers {
+//!     type Serializer = SerdeQuery; // sends GET /users?name=...&limit=...
+//!     // ...
+//! }
+//! ```
+//!
+//! To send only part of a request in the query string, implement
+//! `SerializeBody` for your own strategy type, and override
+//! `SerializeBody::serialize_query`. `serde_urlencoded::to_string` can be used
+//! to serialize the fields that belong in the query string.
+//!
+//! ### File uploads
+//!
+//! Requests that upload files use `Multipart` as their `Serializer`, and
+//! implement `ToMultipart` to describe their text fields and files. The body is
+//! sent as `multipart/form-data`, with a boundary that does not appear in any
+//! of the values. A file whose content type contains a line break or a double
+//! quote is not sent, and fails with `Error::SerializationError`.
+//!
+//! ```ignore
+//! struct UploadAvatar {
+//!     user_id: u64,
+//!     image: FilePart,
+//! }
+//!
+//! impl Request for UploadAvatar {
+//!     type Serializer = Multipart;
+//!     // ...
+//! }
+//!
+//! impl ToMultipart for UploadAvatar {
+//!     fn to_multipart(&self) -> MultipartForm<'_> {
+//!         MultipartForm::new()
+//!             .text("user_id", self.user_id.to_string())
+//!             .file("image", &self.image)
+//!     }
+//! }
+//!
+//! let file = tokio::fs::File::open("avatar.png").await?;
+//! let image = FilePart::read_async("avatar.png", "image/png", file).await?;
+//! client.send(UploadAvatar { user_id: 123, image }).await?;
+//! ```
+//!
+//! ### Error responses
+//!
+//! Responses with a status code outside the 200 range are returned as
+//! `Error::Api`, with the body deserialized into the request's
+//! `ErrorResponse` type. This is useful for APIs that return a structured
+//! error object.
+//!
+//! ```ignore
+//! match client.send(MyRequest::new()).await {
+//!     Ok(response) => println!("{response:?}"),
+//!     Err(Error::Api { status, body, .. }) => println!("{status}: {}", body.detail),
+//!     Err(other) => return Err(other.into()),
+//! }
 //! ```
 //!
+//! Use `String` as the `ErrorResponse` to get the raw body. Other types are
+//! decoded by the request's `Deserializer`, see
+//! `DeserializeBody::deserialize_error`. If the body cannot be deserialized
+//! into the `ErrorResponse` type, `Error::InvalidStatusCode` is returned with
+//! the raw body instead.
+//!
 //! ### Request groups
 //!
 //! You can also define request groups. This defines a client type that is
@@ -113,12 +688,20 @@
 //! compile if you try to send a request with the wrong client.
 //!
 //! ```rust
+//! # use http_typed::*;
+//! # http_typed::__doc_requests!(MyRequest1, MyRequest2);
 //! request_group!(MyApi { MyRequest1, MyRequest2 });
 //! ```
-//! ```rust
-//! let my_client = Client::<MyApi>::new("http://example.com");
+//! ```compile_fail
+//! # use http_typed::*;
+//! # http_typed::__doc_requests!(MyRequest1, MyRequest2, OtherRequest);
+//! # request_group!(MyApi { MyRequest1, MyRequest2 });
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let my_client = Client::<MyApi>::new("http://example.com".into())?;
 //! let my_response1 = my_client.send(MyRequest1::new()).await?; // works
 //! let other_response = my_client.send(OtherRequest::new()).await?; // does not compile
+//! # Ok(())
+//! # }
 //! ```
 //!
 //! ### send_to
@@ -126,26 +709,43 @@
 //! If you want to restrict the request group, but still want to include the url
 //! for every call to `send`, `MyClient` has a `send_to` method that can be used
 //! with the default client to specify the url at the call-site.
-//! ```rust
+//! ```compile_fail
+//! # use http_typed::*;
+//! # http_typed::__doc_requests!(MyRequest1, MyRequest2, OtherRequest);
+//! # request_group!(MyApi { MyRequest1, MyRequest2 });
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
 //! let my_client = Client::<MyApi>::default();
 //! let my_response2 = my_client.send_to("http://example.com", MyRequest2::new()).await?; // works
 //! let other_response = my_client.send_to("http://example.com", OtherRequest::new()).await?; // does not compile
+//! # Ok(())
+//! # }
 //! ```
 //!
 //! The send_to method can also be used to insert a string after the base_url
 //! and before the Request path.
 //!
 //! ```rust
-//! let my_client = Client::new("http://example.com");
+//! # use http_typed::*;
+//! # http_typed::__doc_requests!(MyRequest);
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let my_client = Client::<All>::new("http://example.com".into())?;
 //! let my_response = my_client.send_to("/api/v2", MyRequest::new()).await?;
+//! # Ok(())
+//! # }
 //! ```
 //!
+//! The base url is validated when the client is created, and the base url,
+//! url infix, and request path are joined as urls rather than as strings: each
+//! part is appended to the previous path with exactly one `/` between them,
+//! and query strings from every part are kept. `Client::url` shows the url a
+//! request will be sent to, and documents the rules in full.
+//!
 //! ## Cargo Features
 //!
 //! Typically, the default features should be fine:
 //!
 //! ```toml
-//! http-typed = "0.3"
+//! http-typed = "0.5"
 //! ```
 //!
 //! The default features include the full Client implementation, and depend on
@@ -156,9 +756,31 @@
 //! - **default** = ["client", "native-tls"]
 //! - **client**: Includes the Client implementation described above and depends
 //!   on reqwest.
+//! - **client-core**: Includes the Client implementation without reqwest. A
+//!   `Transport` must be provided to send requests.
 //! - **native-tls**: Depend on dynamically linked system tls libraries.
 //! - **rustls-tls**: Statically link all tls dependencies with webpki, no tls
 //!   is required in the system.
+//! - **msgpack**: Includes the `MessagePack` serializer and deserializer.
+//! - **derive**: Includes `#[derive(Request)]`.
+//! - **mock**: Includes `MockClient` for unit tests.
+//! - **cassette**: Includes the `Cassette` middleware to record and replay
+//!   requests.
+//! - **blocking**: Includes `blocking::Client`, a synchronous version of the
+//!   Client.
+//! - **server**: Includes `DeserializeRequest` and `SerializeResponse` to
+//!   handle requests on a server.
+//! - **axum**: Includes `Routes`, to build an axum Router from Request types.
+//! - **openapi**: Includes `OpenApi`, to generate an OpenAPI document from
+//!   request groups.
+//! - **oauth2**: Includes `ClientCredentials`, a `TokenProvider` for the
+//!   OAuth2 client credentials grant.
+//! - **tokio**: Includes `FilePart::read_async`, to read files to upload from
+//!   a tokio `AsyncRead`.
+//! - **hmac**: Includes `HmacSigner` and `HmacVerifier`, to sign requests with
+//!   HMAC-SHA256 and check their signatures.
+//! - **sigv4**: Includes `SigV4Signer`, to sign requests with AWS Signature
+//!   Version 4.
 //!
 //!
 //! ### No system tls? Use rustls
@@ -166,7 +788,7 @@
 //! To statically link the tls dependencies, use this:
 //!
 //! ```toml
-//! http-typed = { version = "0.3", default-features = false, features = ["client", "rustls-tls"] }
+//! http-typed = { version = "0.5", default-features = false, features = ["client", "rustls-tls"] }
 //! ```
 //!
 //! ### No Client
@@ -175,7 +797,7 @@
 //! dependencies on reqwest and tls libraries, use this:
 //!
 //! ```toml
-//! http-typed = { version = "0.3", default-features = false }
+//! http-typed = { version = "0.5", default-features = false }
 //! ```
 //!
 //! This allows you, as a server developer, to exclude unnecessary dependencies
@@ -239,37 +861,183 @@
 //! meaning the other crate can be used as a drop-in replacement of this one
 //! without changing any code, just with more customization available.
 
-#[cfg(feature = "client")]
+#[cfg(feature = "client-core")]
+mod auth;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(feature = "cassette")]
+mod cassette;
+#[cfg(feature = "client-core")]
 mod client;
+#[cfg(feature = "hmac")]
+mod hmac;
+#[cfg(feature = "client-core")]
+mod middleware;
+#[cfg(feature = "mock")]
+mod mock;
+mod multipart;
+#[cfg(feature = "oauth2")]
+mod oauth2;
+#[cfg(feature = "openapi")]
+mod openapi;
+#[cfg(feature = "client-core")]
+mod retry;
+#[cfg(feature = "server")]
+mod server;
+#[cfg(feature = "sigv4")]
+mod sigv4;
+#[cfg(all(test, feature = "client-core"))]
+mod testing;
+#[cfg(feature = "client-core")]
+mod transport;
 
 use std::convert::Infallible;
 
-#[cfg(feature = "client")]
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use serde::de::{value::StringDeserializer, IntoDeserializer, Visitor};
+
+#[cfg(feature = "client-core")]
+pub use auth::*;
+#[cfg(feature = "cassette")]
+pub use cassette::*;
+#[cfg(feature = "client-core")]
 pub use client::*;
+#[cfg(feature = "hmac")]
+pub use hmac::*;
+#[cfg(feature = "derive")]
+pub use http_typed_derive::Request;
+#[cfg(feature = "client-core")]
+pub use middleware::*;
+#[cfg(feature = "mock")]
+pub use mock::*;
+pub use multipart::*;
+#[cfg(feature = "oauth2")]
+pub use oauth2::*;
+#[cfg(feature = "openapi")]
+pub use openapi::*;
+#[cfg(feature = "client-core")]
+pub use retry::*;
+#[cfg(feature = "server")]
+pub use server::*;
+#[cfg(feature = "sigv4")]
+pub use sigv4::*;
+#[cfg(feature = "client-core")]
+pub use transport::*;
 
 pub trait Request: Sized {
     // TODO: use when stable: https://github.com/rust-lang/rust/issues/29661
     /// Specify a pre-defined approach to serialize a request body. For example:
     /// - SerdeJson
+    /// - SerdeQuery
+    /// - FormUrlEncoded
     /// - NoBody
     type Serializer: SerializeBody<Self>;
 
+    /// Specify a pre-defined approach to deserialize a response body. For
+    /// example:
+    /// - SerdeJson
+    /// - PlainText
+    /// - RawBytes
+    /// - NoBody
+    type Deserializer: DeserializeBody<Self::Response>;
+
     /// Type to deserialize from the http response body
     type Response;
 
+    /// Type to deserialize from the http response body when the status code is
+    /// outside the 200 range. String types such as `String` get the raw body
+    /// unchanged. Other types are parsed from json, falling back to the raw
+    /// body for types that can be deserialized from a string, such as enums.
+    type ErrorResponse;
+
     /// HTTP method that the request will be sent with
     fn method(&self) -> HttpMethod;
 
-    /// String to appended to the end of url when sending this request.
+    /// String to appended to the end of url when sending this request. Use
+    /// `Path` to build it, so that values such as ids are percent-encoded.
     fn path(&self) -> String;
+
+    /// Additional http headers to send with this request, as (name, value)
+    /// pairs. These replace any default headers with the same name, such as
+    /// Content-Type and Accept.
+    fn headers(&self) -> Vec<(String, String)> {
+        vec![]
+    }
+
+    /// Whether the request can safely be sent more than once. Clients only
+    /// retry idempotent requests. Override this to opt a request with a
+    /// non-idempotent method into retries.
+    fn idempotent(&self) -> bool {
+        self.method().is_idempotent()
+    }
+
+    /// Whether the client's `Auth` is added to this request. Override this to
+    /// send a request without credentials, such as a public health check.
+    fn authenticate(&self) -> bool {
+        true
+    }
+}
+
+/// The method and path template of a Request type, known without an instance
+/// of the request. Servers use this to route incoming requests to the matching
+/// type. `#[derive(Request)]` implements this along with Request, using the
+/// same method and path, so that clients and servers always agree on them.
+pub trait Route: Request {
+    const METHOD: HttpMethod;
+
+    /// Path with each parameter written as `{field}`, such as `/users/{id}`.
+    /// The parameters are the fields of the request that `Request::path`
+    /// interpolates.
+    const PATH: &'static str;
 }
 
 pub struct SerdeJson;
 pub struct NoBody;
+/// Request is serialized into the url's query string instead of the body.
+/// Fields that are `None` are left out. A field cannot be a sequence, but a
+/// request that serializes as a sequence of pairs repeats the names.
+pub struct SerdeQuery;
+/// Request body is encoded as `application/x-www-form-urlencoded`, like an
+/// html form. Used by OAuth2 token endpoints and many older APIs.
+pub struct FormUrlEncoded;
+/// Request body is encoded as `multipart/form-data`, for file uploads. The
+/// request describes its fields by implementing `ToMultipart`.
+pub struct Multipart;
+/// Response body is read as a utf-8 `String`.
+pub struct PlainText;
+/// Response body is returned as raw bytes in a `Vec<u8>`.
+pub struct RawBytes;
+/// Body is encoded as MessagePack, with struct fields serialized as a map.
+#[cfg(feature = "msgpack")]
+pub struct MessagePack;
 
 pub trait SerializeBody<T> {
     type Error;
     fn serialize_body(request: &T) -> Result<Vec<u8>, Self::Error>;
+
+    /// Percent-encoded query string to append to the url, without the leading
+    /// `?`. Most serializers only produce a body, and use the default of None.
+    fn serialize_query(_request: &T) -> Result<Option<String>, Self::Error> {
+        Ok(None)
+    }
+
+    /// Value of the Content-Type header sent with the serialized body, which
+    /// defaults to json. The request is provided for formats where the content
+    /// type depends on it, such as a multipart boundary. Clients do not send
+    /// a Content-Type when the body is empty.
+    fn content_type(_request: &T) -> Option<String> {
+        Some("application/json".to_owned())
+    }
+
+    /// The serialized body and its Content-Type, which is what clients use to
+    /// build the request. Defaults to calling `serialize_body` and
+    /// `content_type`. Override it when both need the same work, such as
+    /// finding a multipart boundary.
+    fn serialize_body_and_content_type(
+        request: &T,
+    ) -> Result<(Vec<u8>, Option<String>), Self::Error> {
+        Ok((Self::serialize_body(request)?, Self::content_type(request)))
+    }
 }
 
 impl<T> SerializeBody<T> for SerdeJson
@@ -289,6 +1057,290 @@ impl<T> SerializeBody<T> for NoBody {
     fn serialize_body(_: &T) -> Result<Vec<u8>, Self::Error> {
         Ok(vec![])
     }
+
+    fn content_type(_: &T) -> Option<String> {
+        None
+    }
+}
+
+impl<T> SerializeBody<T> for SerdeQuery
+where
+    T: serde::Serialize,
+{
+    type Error = serde_urlencoded::ser::Error;
+
+    fn serialize_body(_: &T) -> Result<Vec<u8>, Self::Error> {
+        Ok(vec![])
+    }
+
+    fn serialize_query(request: &T) -> Result<Option<String>, Self::Error> {
+        serde_urlencoded::to_string(request).map(Some)
+    }
+
+    fn content_type(_: &T) -> Option<String> {
+        None
+    }
+}
+
+impl<T> SerializeBody<T> for FormUrlEncoded
+where
+    T: serde::Serialize,
+{
+    type Error = serde_urlencoded::ser::Error;
+
+    fn serialize_body(request: &T) -> Result<Vec<u8>, Self::Error> {
+        Ok(serde_urlencoded::to_string(request)?.into_bytes())
+    }
+
+    fn content_type(_: &T) -> Option<String> {
+        Some("application/x-www-form-urlencoded".to_owned())
+    }
+}
+
+#[cfg(feature = "msgpack")]
+impl<T> SerializeBody<T> for MessagePack
+where
+    T: serde::Serialize,
+{
+    type Error = rmp_serde::encode::Error;
+
+    fn serialize_body(request: &T) -> Result<Vec<u8>, Self::Error> {
+        rmp_serde::to_vec_named(request)
+    }
+
+    fn content_type(_: &T) -> Option<String> {
+        Some("application/msgpack".to_owned())
+    }
+}
+
+pub trait DeserializeBody<T> {
+    type Error;
+    fn deserialize_body(body: &[u8]) -> Result<T, Self::Error>;
+
+    /// Value of the Accept header that clients send, naming the media type
+    /// that this deserializer reads. Defaults to None, which sends no Accept
+    /// header.
+    fn accept() -> Option<&'static str> {
+        None
+    }
+
+    /// Deserialize the body of a response with a status code outside the 200
+    /// range into `Request::ErrorResponse`, or None if it does not match. By
+    /// default, string types get the raw body unchanged, and other types are
+    /// parsed from json, falling back to the raw body for types that can be
+    /// deserialized from a string, such as enums. Formats that APIs also use
+    /// for their error bodies, such as MessagePack, try their own format first.
+    fn deserialize_error<E>(body: &[u8]) -> Option<E>
+    where
+        E: for<'a> serde::Deserialize<'a>,
+    {
+        deserialize_error_body(body)
+    }
+}
+
+impl<T> DeserializeBody<T> for SerdeJson
+where
+    T: for<'a> serde::Deserialize<'a>,
+{
+    type Error = serde_json::error::Error;
+
+    fn deserialize_body(body: &[u8]) -> Result<T, Self::Error> {
+        serde_json::from_slice(body)
+    }
+
+    fn accept() -> Option<&'static str> {
+        Some("application/json")
+    }
+}
+
+/// Ignores the response body.
+impl<T: Default> DeserializeBody<T> for NoBody {
+    type Error = Infallible;
+
+    fn deserialize_body(_: &[u8]) -> Result<T, Self::Error> {
+        Ok(T::default())
+    }
+}
+
+impl DeserializeBody<String> for PlainText {
+    type Error = std::string::FromUtf8Error;
+
+    fn deserialize_body(body: &[u8]) -> Result<String, Self::Error> {
+        String::from_utf8(body.to_vec())
+    }
+
+    fn accept() -> Option<&'static str> {
+        Some("text/plain")
+    }
+}
+
+impl DeserializeBody<Vec<u8>> for RawBytes {
+    type Error = Infallible;
+
+    fn deserialize_body(body: &[u8]) -> Result<Vec<u8>, Self::Error> {
+        Ok(body.to_vec())
+    }
+}
+
+#[cfg(feature = "msgpack")]
+impl<T> DeserializeBody<T> for MessagePack
+where
+    T: for<'a> serde::Deserialize<'a>,
+{
+    type Error = rmp_serde::decode::Error;
+
+    fn deserialize_body(body: &[u8]) -> Result<T, Self::Error> {
+        rmp_serde::from_slice(body)
+    }
+
+    fn accept() -> Option<&'static str> {
+        Some("application/msgpack")
+    }
+
+    fn deserialize_error<E>(body: &[u8]) -> Option<E>
+    where
+        E: for<'a> serde::Deserialize<'a>,
+    {
+        rmp_serde::from_slice(body)
+            .ok()
+            .or_else(|| deserialize_error_body(body))
+    }
+}
+
+/// Gives string types such as `String` the raw body, then attempts to parse
+/// the body as json, then falls back to deserializing the raw body as a
+/// string, for types such as enums of unit variants.
+fn deserialize_error_body<ErrRes>(body: &[u8]) -> Option<ErrRes>
+where
+    ErrRes: for<'a> serde::Deserialize<'a>,
+{
+    let text = std::str::from_utf8(body).ok();
+    if let Some(error_response) = text.and_then(|text| ErrRes::deserialize(RawString(text)).ok()) {
+        return Some(error_response);
+    }
+    if let Ok(error_response) = serde_json::from_slice(body) {
+        return Some(error_response);
+    }
+    let raw: StringDeserializer<serde::de::value::Error> = text?.to_owned().into_deserializer();
+    ErrRes::deserialize(raw).ok()
+}
+
+/// A deserializer that only succeeds for types that ask for a string, so that
+/// a json body is not parsed into a string type, which would strip its quotes,
+/// and other types such as `serde_json::Value` are still parsed as json.
+struct RawString<'a>(&'a str);
+
+impl<'de> serde::Deserializer<'de> for RawString<'_> {
+    type Error = serde::de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+        Err(serde::de::Error::custom("not a string type"))
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_str(self.0)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_str(self.0)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char bytes byte_buf
+        option unit unit_struct seq tuple tuple_struct map struct enum identifier
+        ignored_any
+    }
+}
+
+/// Characters that must be percent-encoded in a url path segment, per RFC 3986.
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+/// Percent-encode a string so it can be used as a single segment of a url
+/// path. Any `/` in the input is encoded, so it cannot introduce extra
+/// segments, and the segments `.` and `..` are encoded as `%2E` and `%2E%2E`,
+/// so they are not read as the current or parent directory by servers that
+/// follow RFC 3986. Urls resolve the encoded forms too, so `Client` returns an
+/// error for a path with these segments instead of sending it.
+pub fn encode_path_segment(segment: &str) -> String {
+    match segment {
+        "." => "%2E".into(),
+        ".." => "%2E%2E".into(),
+        _ => utf8_percent_encode(segment, PATH_SEGMENT).to_string(),
+    }
+}
+
+/// Builds a url path from segments, percent-encoding each one, so values such
+/// as ids can be included safely even if they contain `/`, spaces, or other
+/// reserved characters. Convert it into a String to return it from
+/// `Request::path`:
+///
+/// ```ignore
+/// fn path(&self) -> String {
+///     Path::new("/users").segment(&self.id).segment("posts").into()
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Path(String);
+
+impl Path {
+    /// Start a path with a prefix, which is used as is, without encoding.
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self(prefix.into())
+    }
+
+    /// Append a `/` and the percent-encoded segment.
+    pub fn segment(mut self, segment: impl std::fmt::Display) -> Self {
+        if !self.0.ends_with('/') {
+            self.0.push('/');
+        }
+        self.0.push_str(&encode_path_segment(&segment.to_string()));
+        self
+    }
+
+    /// Append each of the segments. See `segment`.
+    pub fn segments<T: std::fmt::Display>(self, segments: impl IntoIterator<Item = T>) -> Self {
+        segments.into_iter().fold(self, Self::segment)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for Path {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<Path> for String {
+    fn from(path: Path) -> Self {
+        path.0
+    }
 }
 
 /// Define a request group to constrain which requests can be used with a client.
@@ -300,14 +1352,66 @@ macro_rules! request_group {
     ($viz:vis $Name:ident { $($Request:ident),*$(,)? }) => {
         $viz struct $Name;
         $(impl $crate::InRequestGroup<$Name> for $Request {})*
+        impl $crate::GroupRequests for $Name {
+            type Requests = $crate::request_list!($($Request),*);
+        }
+    };
+}
+
+/// Defines `MyResponse` and a Request type for each name, to keep the examples
+/// in the crate documentation short.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __doc_requests {
+    ($($Request:ident),*) => {
+        #[derive(serde::Deserialize)]
+        struct MyResponse {
+            some_field: String,
+        }
+        $(
+            #[derive(serde::Serialize)]
+            struct $Request;
+            impl $Request {
+                fn new() -> Self {
+                    $Request
+                }
+            }
+            impl $crate::Request for $Request {
+                type Serializer = $crate::SerdeJson;
+                type Deserializer = $crate::SerdeJson;
+                type Response = MyResponse;
+                type ErrorResponse = String;
+                fn method(&self) -> $crate::HttpMethod {
+                    $crate::HttpMethod::Get
+                }
+                fn path(&self) -> String {
+                    "/".into()
+                }
+            }
+        )*
     };
 }
 
+#[doc(hidden)]
+#[macro_export]
+macro_rules! request_list {
+    () => { () };
+    ($First:ident $(, $Rest:ident)*) => { ($First, $crate::request_list!($($Rest),*)) };
+}
+
 /// Indicates that a request is part of a request group. If you use the
 /// request_group macro to define the group, it will handle the implementation
 /// of this trait automatically.
 pub trait InRequestGroup<Group> {}
 
+/// Lists the requests in a request group, so that tools such as `OpenApi` can
+/// enumerate them. The request_group macro implements this automatically.
+pub trait GroupRequests {
+    /// The requests as a nested list of pairs ending in `()`, such as
+    /// `(MyRequest1, (MyRequest2, ()))`.
+    type Requests;
+}
+
 /// The default group. All requests are in this group.
 pub struct All;
 impl<T> InRequestGroup<All> for T {}
@@ -324,3 +1428,178 @@ pub enum HttpMethod {
     Connect,
     Patch,
 }
+
+impl HttpMethod {
+    /// Whether sending the same request multiple times has the same effect as
+    /// sending it once, per RFC 9110.
+    pub fn is_idempotent(&self) -> bool {
+        match self {
+            HttpMethod::Options
+            | HttpMethod::Get
+            | HttpMethod::Put
+            | HttpMethod::Delete
+            | HttpMethod::Head
+            | HttpMethod::Trace => true,
+            HttpMethod::Post | HttpMethod::Connect | HttpMethod::Patch => false,
+        }
+    }
+
+    /// The method name as it is sent in an http request, such as "GET".
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HttpMethod::Options => "OPTIONS",
+            HttpMethod::Get => "GET",
+            HttpMethod::Post => "POST",
+            HttpMethod::Put => "PUT",
+            HttpMethod::Delete => "DELETE",
+            HttpMethod::Head => "HEAD",
+            HttpMethod::Trace => "TRACE",
+            HttpMethod::Connect => "CONNECT",
+            HttpMethod::Patch => "PATCH",
+        }
+    }
+}
+
+/// Lowercase hex encoding, used for signatures and hashes.
+#[cfg(any(feature = "hmac", feature = "sigv4"))]
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    struct Problem {
+        detail: String,
+    }
+
+    #[derive(Debug, PartialEq, serde::Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Code {
+        NotFound,
+    }
+
+    #[test]
+    fn string_error_body_is_raw() {
+        let body = deserialize_error_body::<String>(br#""quoted""#);
+        assert_eq!(body.as_deref(), Some(r#""quoted""#));
+        let body = deserialize_error_body::<String>(br#"{"detail":"x"}"#);
+        assert_eq!(body.as_deref(), Some(r#"{"detail":"x"}"#));
+    }
+
+    #[test]
+    fn structured_error_body_is_json() {
+        let body = deserialize_error_body::<Problem>(br#"{"detail":"x"}"#);
+        assert_eq!(body, Some(Problem { detail: "x".into() }));
+        let body = deserialize_error_body::<serde_json::Value>(br#"{"detail":"x"}"#);
+        assert_eq!(body, Some(serde_json::json!({ "detail": "x" })));
+        assert_eq!(deserialize_error_body::<Problem>(b"not json"), None);
+    }
+
+    #[test]
+    fn enum_error_body_from_json_or_raw() {
+        assert_eq!(
+            deserialize_error_body::<Code>(br#""not_found""#),
+            Some(Code::NotFound)
+        );
+        assert_eq!(
+            deserialize_error_body::<Code>(b"not_found"),
+            Some(Code::NotFound)
+        );
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn msgpack_error_body() {
+        #[derive(serde::Serialize)]
+        struct Body {
+            detail: &'static str,
+        }
+        let body = rmp_serde::to_vec_named(&Body { detail: "x" }).unwrap();
+        let error = <MessagePack as DeserializeBody<()>>::deserialize_error::<Problem>(&body);
+        assert_eq!(error, Some(Problem { detail: "x".into() }));
+        // error bodies from proxies in front of the api are often json
+        let error = <MessagePack as DeserializeBody<()>>::deserialize_error::<Problem>(
+            br#"{"detail":"y"}"#,
+        );
+        assert_eq!(error, Some(Problem { detail: "y".into() }));
+    }
+
+    fn query<T: serde::Serialize>(request: &T) -> Result<Option<String>, String> {
+        SerdeQuery::serialize_query(request).map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn query_is_percent_encoded() {
+        #[derive(serde::Serialize)]
+        struct Search {
+            q: &'static str,
+            page: u32,
+        }
+        let request = Search {
+            q: "a b&c=d/e?f#g+h%i",
+            page: 2,
+        };
+        assert_eq!(
+            query(&request).unwrap().as_deref(),
+            Some("q=a+b%26c%3Dd%2Fe%3Ff%23g%2Bh%25i&page=2")
+        );
+        assert!(SerdeQuery::serialize_body(&request).unwrap().is_empty());
+        assert_eq!(
+            <SerdeQuery as SerializeBody<Search>>::content_type(&request),
+            None
+        );
+    }
+
+    #[test]
+    fn none_fields_are_left_out_of_the_query() {
+        #[derive(serde::Serialize)]
+        struct Search {
+            name: Option<&'static str>,
+            limit: Option<u32>,
+        }
+        let request = Search {
+            name: None,
+            limit: Some(10),
+        };
+        assert_eq!(query(&request).unwrap().as_deref(), Some("limit=10"));
+        let request = Search {
+            name: None,
+            limit: None,
+        };
+        assert_eq!(query(&request).unwrap().as_deref(), Some(""));
+    }
+
+    #[test]
+    fn sequences_in_the_query() {
+        // a sequence of pairs repeats the name
+        let request = [("tag", "a"), ("tag", "b c")];
+        assert_eq!(query(&request).unwrap().as_deref(), Some("tag=a&tag=b+c"));
+
+        // a sequence as the value of a field is not supported
+        #[derive(serde::Serialize)]
+        struct Search {
+            tags: Vec<&'static str>,
+        }
+        let request = Search {
+            tags: vec!["a", "b"],
+        };
+        assert!(query(&request).is_err());
+    }
+
+    #[test]
+    fn dot_segments_are_encoded() {
+        assert_eq!(encode_path_segment("."), "%2E");
+        assert_eq!(encode_path_segment(".."), "%2E%2E");
+        assert_eq!(encode_path_segment("..."), "...");
+        assert_eq!(encode_path_segment("../a"), "..%2Fa");
+        assert_eq!(encode_path_segment("a.b"), "a.b");
+        let path = Path::new("/files")
+            .segment("..")
+            .segment(".")
+            .segment("a.txt");
+        assert_eq!(path.as_str(), "/files/%2E%2E/%2E/a.txt");
+    }
+}
@@ -0,0 +1,171 @@
+use std::sync::{
+    atomic::{AtomicU8, Ordering},
+    Arc,
+};
+
+use http::{HeaderMap, StatusCode};
+
+use crate::{HttpMethod, Transport};
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// An http request as it will be sent, after the Request has been serialized.
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: HttpMethod,
+    pub url: String,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+/// An http response as it was received, before the body has been deserialized.
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    /// The final url of the response, after any redirects.
+    pub url: String,
+    pub body: Vec<u8>,
+}
+
+/// Runs around every request sent by a Client. A middleware can inspect or
+/// modify the request before passing it to `next`, and inspect or modify the
+/// response before returning it. It may also return a response without calling
+/// `next` at all.
+///
+/// ```ignore
+/// struct Log;
+///
+/// #[async_trait::async_trait]
+/// impl Middleware for Log {
+///     async fn handle(&self, request: HttpRequest, next: Next<'_>) -> Result<HttpResponse, BoxError> {
+///         println!("{:?} {}", request.method, request.url);
+///         let response = next.run(request).await?;
+///         println!("{}", response.status);
+///         Ok(response)
+///     }
+/// }
+///
+/// let client = Client::new("http://example.com".into())?.with(Log);
+/// ```
+#[async_trait::async_trait]
+pub trait Middleware: Send + Sync + 'static {
+    async fn handle(&self, request: HttpRequest, next: Next<'_>) -> Result<HttpResponse, BoxError>;
+}
+
+/// Signs every request sent by a Client, by adding headers or query
+/// parameters computed from the method, url, headers and body.
+///
+/// The signer runs after all middleware, immediately before the request is
+/// passed to the transport, so the signature covers the request exactly as it
+/// is sent. Retried requests are signed again. Errors returned by the signer
+/// are surfaced as `Error::AuthError`.
+///
+/// ```ignore
+/// struct Checksum;
+///
+/// impl Signer for Checksum {
+///     fn sign(&self, request: &mut HttpRequest) -> Result<(), BoxError> {
+///         let checksum = crc32(&request.body).to_string();
+///         request.headers.insert("X-Checksum", checksum.parse()?);
+///         Ok(())
+///     }
+/// }
+///
+/// let client = Client::new("http://example.com".into())?.signer(Checksum);
+/// ```
+pub trait Signer: Send + Sync + 'static {
+    fn sign(&self, request: &mut HttpRequest) -> Result<(), BoxError>;
+}
+
+/// The remainder of the middleware chain, ending with the transport.
+#[derive(Clone, Copy)]
+pub struct Next<'a> {
+    transport: &'a dyn Transport,
+    middleware: &'a [Arc<dyn Middleware>],
+    signer: Option<&'a dyn Signer>,
+    stage: &'a AtomicU8,
+}
+
+/// Why a request sent through the middleware chain failed.
+#[derive(Debug)]
+pub(crate) enum SendError {
+    /// A middleware returned an error of its own.
+    Middleware(BoxError),
+    /// The signer could not sign the request.
+    Signer(BoxError),
+    /// The transport could not send the request, and the middleware passed
+    /// the error on.
+    Transport(BoxError),
+}
+
+/// How far the last request reached at the end of the chain.
+const NOT_SENT: u8 = 0;
+const SIGNER_FAILED: u8 = 1;
+const TRANSPORT_FAILED: u8 = 2;
+const SENT: u8 = 3;
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(
+        transport: &'a dyn Transport,
+        middleware: &'a [Arc<dyn Middleware>],
+        signer: Option<&'a dyn Signer>,
+        stage: &'a AtomicU8,
+    ) -> Self {
+        Self {
+            transport,
+            middleware,
+            signer,
+            stage,
+        }
+    }
+
+    /// Run the whole chain, and tell apart errors from the middleware, the
+    /// signer and the transport.
+    pub(crate) async fn send(self, request: HttpRequest) -> Result<HttpResponse, SendError> {
+        self.stage.store(NOT_SENT, Ordering::Relaxed);
+        self.run(request)
+            .await
+            .map_err(|error| match self.stage.load(Ordering::Relaxed) {
+                SIGNER_FAILED => SendError::Signer(error),
+                TRANSPORT_FAILED => SendError::Transport(error),
+                _ => SendError::Middleware(error),
+            })
+    }
+
+    /// Pass the request to the next middleware, or sign and send it if this is
+    /// the end of the chain.
+    pub async fn run(self, mut request: HttpRequest) -> Result<HttpResponse, BoxError> {
+        match self.middleware.split_first() {
+            Some((middleware, rest)) => {
+                middleware
+                    .handle(
+                        request,
+                        Next::new(self.transport, rest, self.signer, self.stage),
+                    )
+                    .await
+            }
+            None => {
+                if let Some(signer) = self.signer {
+                    if let Err(error) = signer.sign(&mut request) {
+                        self.stage.store(SIGNER_FAILED, Ordering::Relaxed);
+                        return Err(error);
+                    }
+                }
+                let response = self.transport.send(request).await;
+                let stage = if response.is_ok() {
+                    SENT
+                } else {
+                    TRANSPORT_FAILED
+                };
+                self.stage.store(stage, Ordering::Relaxed);
+                response
+            }
+        }
+    }
+
+    /// The transport at the end of the chain.
+    pub fn transport(&self) -> &'a dyn Transport {
+        self.transport
+    }
+}
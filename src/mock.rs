@@ -0,0 +1,347 @@
+use std::{
+    any::{type_name, Any, TypeId},
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use http::{HeaderMap, StatusCode};
+
+use crate::{All, Error, HttpMethod, InRequestGroup, Request, RequestError, Response, SimpleBody};
+
+/// A stand-in for Client in unit tests, which returns canned responses
+/// instead of sending http requests. Responses are registered for each request
+/// type, and only requests in the RequestGroup can be registered or sent.
+///
+/// The send methods have the same names and signatures as the ones on Client,
+/// except that requests must also be `Send + 'static` so that they can be
+/// recorded. Code that uses a Client can be tested by swapping in a
+/// MockClient, for example with a type alias:
+///
+/// ```ignore
+/// #[cfg(not(test))]
+/// type ApiClient = Client<MyApi>;
+/// #[cfg(test)]
+/// type ApiClient = MockClient<MyApi>;
+///
+/// let client = ApiClient::default();
+/// client.on::<GetUser>(|request| Ok(User { id: request.id.clone() }));
+/// client.on::<DeleteUser>(|_| Err(Error::Api { status: 403, headers: Default::default(), body: "forbidden".into() }));
+///
+/// my_service(&client).await;
+/// assert_eq!(client.call_count::<GetUser>(), 1);
+/// ```
+///
+/// Clones share the same responses and recorded calls.
+pub struct MockClient<RequestGroup = All> {
+    state: Arc<MockState>,
+    _p: PhantomData<fn() -> RequestGroup>,
+}
+
+#[derive(Default)]
+struct MockState {
+    /// Handler<Req> for each request type, and CustomHandler<Req, Res> for
+    /// each request and response type, keyed by the type of the handler
+    handlers: Mutex<HashMap<TypeId, Box<dyn Any + Send + Sync>>>,
+    /// Every request that has been sent, in order
+    calls: Mutex<Vec<MockCall>>,
+}
+
+type Handler<Req> =
+    Arc<dyn Fn(&Req) -> Result<<Req as Request>::Response, RequestError<Req>> + Send + Sync>;
+
+type CustomHandler<Req, Res> = Arc<
+    dyn Fn(HttpMethod, &str, &Req) -> Result<Res, Error<<Req as SimpleBody>::Error>> + Send + Sync,
+>;
+
+struct MockCall {
+    type_id: TypeId,
+    url: String,
+    request: Box<dyn Any + Send>,
+}
+
+/// Explicitly implemented to avoid requirement RequestGroup: Debug
+impl<RequestGroup> std::fmt::Debug for MockClient<RequestGroup> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(type_name::<Self>())
+            .field("calls", &self.urls())
+            .finish()
+    }
+}
+
+/// Explicitly implemented to avoid requirement RequestGroup: Default
+impl<RequestGroup> Default for MockClient<RequestGroup> {
+    fn default() -> Self {
+        Self {
+            state: Default::default(),
+            _p: PhantomData,
+        }
+    }
+}
+
+/// Explicitly implemented to avoid requirement RequestGroup: Clone
+impl<RequestGroup> Clone for MockClient<RequestGroup> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<RequestGroup> MockClient<RequestGroup> {
+    /// Respond to every request of type Req by calling the handler. Replaces
+    /// any handler previously registered for Req.
+    pub fn on<Req>(
+        &self,
+        handler: impl Fn(&Req) -> Result<Req::Response, RequestError<Req>> + Send + Sync + 'static,
+    ) -> &Self
+    where
+        Req: Request + InRequestGroup<RequestGroup> + 'static,
+    {
+        let handler: Handler<Req> = Arc::new(handler);
+        self.state
+            .handlers
+            .lock()
+            .unwrap()
+            .insert(TypeId::of::<Handler<Req>>(), Box::new(handler));
+        self
+    }
+
+    /// Respond to every `send_custom` call with a request of type Req and a
+    /// response of type Res by calling the handler with the method, path and
+    /// request. Replaces any handler previously registered for Req and Res.
+    pub fn on_custom<Req, Res>(
+        &self,
+        handler: impl Fn(HttpMethod, &str, &Req) -> Result<Res, Error<Req::Error>>
+            + Send
+            + Sync
+            + 'static,
+    ) -> &Self
+    where
+        Req: SimpleBody + 'static,
+        Res: 'static,
+    {
+        let handler: CustomHandler<Req, Res> = Arc::new(handler);
+        self.state
+            .handlers
+            .lock()
+            .unwrap()
+            .insert(TypeId::of::<CustomHandler<Req, Res>>(), Box::new(handler));
+        self
+    }
+
+    /// Send the request to the handler registered for its type. If there is
+    /// no handler, this returns `Error::InvalidStatusCode` with status 404.
+    pub async fn send<Req>(&self, request: Req) -> Result<Req::Response, RequestError<Req>>
+    where
+        Req: Request + InRequestGroup<RequestGroup> + Send + 'static,
+        Req::ErrorResponse: for<'a> serde::Deserialize<'a>,
+    {
+        let url = request.path();
+        self.respond(url, request)
+    }
+
+    /// Same as `send`, but the url_infix is included in the url recorded for
+    /// the call.
+    pub async fn send_to<Req>(
+        &self,
+        url_infix: &str,
+        request: Req,
+    ) -> Result<Req::Response, RequestError<Req>>
+    where
+        Req: Request + InRequestGroup<RequestGroup> + Send + 'static,
+        Req::ErrorResponse: for<'a> serde::Deserialize<'a>,
+    {
+        let url = format!("{url_infix}{}", request.path());
+        self.respond(url, request)
+    }
+
+    /// Same as `send`, with a status of 200 and no headers in the metadata.
+    pub async fn send_with_meta<Req>(
+        &self,
+        request: Req,
+    ) -> Result<Response<Req::Response>, RequestError<Req>>
+    where
+        Req: Request + InRequestGroup<RequestGroup> + Send + 'static,
+        Req::ErrorResponse: for<'a> serde::Deserialize<'a>,
+    {
+        let url = request.path();
+        Ok(Response {
+            value: self.respond(url.clone(), request)?,
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            url,
+            elapsed: Duration::ZERO,
+        })
+    }
+
+    /// Same as `send_to`, with a status of 200 and no headers in the metadata.
+    pub async fn send_to_with_meta<Req>(
+        &self,
+        url_infix: &str,
+        request: Req,
+    ) -> Result<Response<Req::Response>, RequestError<Req>>
+    where
+        Req: Request + InRequestGroup<RequestGroup> + Send + 'static,
+        Req::ErrorResponse: for<'a> serde::Deserialize<'a>,
+    {
+        let url = format!("{url_infix}{}", request.path());
+        Ok(Response {
+            value: self.respond(url.clone(), request)?,
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            url,
+            elapsed: Duration::ZERO,
+        })
+    }
+
+    /// Send the request to the handler registered with `on_custom` for Req and
+    /// Res. If there is no handler, this returns `Error::InvalidStatusCode`
+    /// with status 404.
+    pub async fn send_custom<Req, Res>(
+        &self,
+        path: &str,
+        method: HttpMethod,
+        request: Req,
+    ) -> Result<Res, Error<Req::Error>>
+    where
+        Req: SimpleBody + Send + 'static,
+        Res: for<'a> serde::Deserialize<'a> + 'static,
+    {
+        let handler = self.handler::<CustomHandler<Req, Res>>();
+        let response = match handler {
+            Some(handler) => handler(method, path, &request),
+            None => Err(not_found(&format!(
+                "{} with response {}",
+                type_name::<Req>(),
+                type_name::<Res>()
+            ))),
+        };
+        self.record::<Req>(path.to_owned(), request);
+        response
+    }
+
+    /// All requests of type Req that have been sent, in order.
+    pub fn requests<Req: Clone + 'static>(&self) -> Vec<Req> {
+        self.state
+            .calls
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|call| call.request.downcast_ref::<Req>().cloned())
+            .collect()
+    }
+
+    /// Number of requests of type Req that have been sent.
+    pub fn call_count<Req: 'static>(&self) -> usize {
+        self.state
+            .calls
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|call| call.type_id == TypeId::of::<Req>())
+            .count()
+    }
+
+    /// The url path of every request that has been sent, in order.
+    pub fn urls(&self) -> Vec<String> {
+        let calls = self.state.calls.lock().unwrap();
+        calls.iter().map(|call| call.url.clone()).collect()
+    }
+
+    fn respond<Req>(&self, url: String, request: Req) -> Result<Req::Response, RequestError<Req>>
+    where
+        Req: Request + Send + 'static,
+    {
+        let response = match self.handler::<Handler<Req>>() {
+            Some(handler) => handler(&request),
+            None => Err(not_found(type_name::<Req>())),
+        };
+        self.record(url, request);
+        response
+    }
+
+    /// Handlers are stored under their own type, which includes the request
+    /// and response types.
+    fn handler<H: Clone + 'static>(&self) -> Option<H> {
+        let handlers = self.state.handlers.lock().unwrap();
+        handlers
+            .get(&TypeId::of::<H>())?
+            .downcast_ref::<H>()
+            .cloned()
+    }
+
+    fn record<Req: Send + 'static>(&self, url: String, request: Req) {
+        self.state.calls.lock().unwrap().push(MockCall {
+            type_id: TypeId::of::<Req>(),
+            url,
+            request: Box::new(request),
+        });
+    }
+}
+
+fn not_found<Ser, ErrRes, De>(request: &str) -> Error<Ser, ErrRes, De> {
+    Error::InvalidStatusCode(404, format!("no mock response registered for {request}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testing::Ping, SerdeJson};
+
+    #[derive(serde::Serialize)]
+    struct Search {
+        term: String,
+    }
+
+    impl SimpleBody for Search {
+        type Error = serde_json::Error;
+
+        fn simple_body(&self) -> Result<Vec<u8>, Self::Error> {
+            <SerdeJson as crate::SerializeBody<Self>>::serialize_body(self)
+        }
+    }
+
+    #[tokio::test]
+    async fn send_to_with_meta_records_the_infix() {
+        let client = MockClient::<All>::default();
+        client.on::<Ping>(|_| Ok("pong".into()));
+        let response = client.send_to_with_meta("/v1", Ping).await.unwrap();
+        assert_eq!(response.value, "pong");
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.url, "/v1/ping");
+        assert_eq!(client.urls(), ["/v1/ping"]);
+        assert_eq!(client.call_count::<Ping>(), 1);
+    }
+
+    #[tokio::test]
+    async fn send_custom_uses_the_handler_for_request_and_response() {
+        let client = MockClient::<All>::default();
+        client.on_custom::<Search, Vec<String>>(|method, path, search| {
+            assert!(matches!(method, HttpMethod::Post));
+            Ok(vec![format!("{path}:{}", search.term)])
+        });
+        let search = || Search { term: "x".into() };
+        let found: Vec<String> = client
+            .send_custom("/search", HttpMethod::Post, search())
+            .await
+            .unwrap();
+        assert_eq!(found, ["/search:x"]);
+        let other: Result<String, _> = client
+            .send_custom("/search", HttpMethod::Post, search())
+            .await;
+        assert!(matches!(other, Err(Error::InvalidStatusCode(404, _))));
+        assert_eq!(client.call_count::<Search>(), 2);
+    }
+
+    #[tokio::test]
+    async fn unregistered_request_is_not_found() {
+        let client = MockClient::<All>::default();
+        client.on_custom::<Search, String>(|_, _, _| Ok("custom".into()));
+        let result = client.send(Ping).await;
+        assert!(matches!(result, Err(Error::InvalidStatusCode(404, _))));
+        assert_eq!(client.urls(), ["/ping"]);
+    }
+}
@@ -0,0 +1,366 @@
+use std::{borrow::Cow, io::Read};
+
+use crate::{Multipart, SerializeBody};
+
+/// Describes the fields of a request that is sent as `multipart/form-data`
+/// with the `Multipart` serializer.
+///
+/// ```ignore
+/// struct UploadDocument {
+///     title: String,
+///     file: FilePart,
+/// }
+///
+/// impl ToMultipart for UploadDocument {
+///     fn to_multipart(&self) -> MultipartForm<'_> {
+///         MultipartForm::new()
+///             .text("title", &self.title)
+///             .file("file", &self.file)
+///     }
+/// }
+/// ```
+pub trait ToMultipart {
+    fn to_multipart(&self) -> MultipartForm<'_>;
+}
+
+/// The text fields and files of a `multipart/form-data` body, in the order
+/// they are sent. Values are borrowed from the request, so that files are not
+/// copied before they are encoded.
+#[derive(Debug, Clone, Default)]
+pub struct MultipartForm<'a> {
+    parts: Vec<Part<'a>>,
+}
+
+/// A file to upload in a `MultipartForm`. The content is held in memory, like
+/// every request body. In async code, read it with `read_async`, which needs
+/// the `tokio` feature, or another async api such as `tokio::fs::read`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct FilePart {
+    pub filename: String,
+    pub content_type: String,
+    pub content: Vec<u8>,
+}
+
+/// Returned by `Multipart` when the content type of a file cannot be sent in
+/// its part's Content-Type header, because it contains a line break or a
+/// double quote.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid content type for multipart field `{name}`: {content_type:?}")]
+pub struct InvalidContentType {
+    pub name: String,
+    pub content_type: String,
+}
+
+#[derive(Debug, Clone)]
+struct Part<'a> {
+    name: Cow<'a, str>,
+    filename: Option<&'a str>,
+    content_type: Option<&'a str>,
+    content: Cow<'a, [u8]>,
+}
+
+impl<'a> MultipartForm<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a text field, sent without a filename or content type.
+    pub fn text(mut self, name: impl Into<Cow<'a, str>>, value: impl Into<Cow<'a, str>>) -> Self {
+        let content = match value.into() {
+            Cow::Borrowed(value) => Cow::Borrowed(value.as_bytes()),
+            Cow::Owned(value) => Cow::Owned(value.into_bytes()),
+        };
+        self.parts.push(Part {
+            name: name.into(),
+            filename: None,
+            content_type: None,
+            content,
+        });
+        self
+    }
+
+    /// Add a file, sent with its filename and content type.
+    pub fn file(mut self, name: impl Into<Cow<'a, str>>, file: &'a FilePart) -> Self {
+        self.parts.push(Part {
+            name: name.into(),
+            filename: Some(&file.filename),
+            content_type: Some(&file.content_type),
+            content: Cow::Borrowed(&file.content),
+        });
+        self
+    }
+
+    /// The boundary that separates the parts of the encoded form. It is the
+    /// first one of the form `http-typed-boundary-{n}` that does not appear in
+    /// any of the values, so the same form is always encoded the same way.
+    pub fn boundary(&self) -> String {
+        (0u64..)
+            .map(|n| format!("http-typed-boundary-{n}"))
+            .find(|boundary| {
+                !self
+                    .parts
+                    .iter()
+                    .any(|part| contains(&part.content, boundary.as_bytes()))
+            })
+            .expect("some boundary is not in the form")
+    }
+
+    /// The `multipart/form-data` Content-Type, with the boundary.
+    pub fn content_type(&self) -> String {
+        content_type(&self.boundary())
+    }
+
+    /// Encode the form, and return it with its Content-Type. The boundary is
+    /// only searched for once.
+    pub fn encode(&self) -> Result<(Vec<u8>, String), InvalidContentType> {
+        let boundary = self.boundary();
+        Ok((self.encode_with(&boundary)?, content_type(&boundary)))
+    }
+
+    /// Encode the form, with the parts separated by `boundary`.
+    fn encode_with(&self, boundary: &str) -> Result<Vec<u8>, InvalidContentType> {
+        let mut body = Vec::new();
+        for part in &self.parts {
+            body.extend_from_slice(format!("--{boundary}\r\n").as_bytes());
+            let mut disposition = format!("form-data; name=\"{}\"", escape(&part.name));
+            if let Some(filename) = part.filename {
+                disposition.push_str(&format!("; filename=\"{}\"", escape(filename)));
+            }
+            body.extend_from_slice(format!("Content-Disposition: {disposition}\r\n").as_bytes());
+            if let Some(content_type) = part.content_type {
+                if content_type.contains(['"', '\r', '\n']) {
+                    return Err(InvalidContentType {
+                        name: part.name.to_string(),
+                        content_type: content_type.to_owned(),
+                    });
+                }
+                body.extend_from_slice(format!("Content-Type: {content_type}\r\n").as_bytes());
+            }
+            body.extend_from_slice(b"\r\n");
+            body.extend_from_slice(&part.content);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+        Ok(body)
+    }
+}
+
+impl FilePart {
+    pub fn new(
+        filename: impl Into<String>,
+        content_type: impl Into<String>,
+        content: impl Into<Vec<u8>>,
+    ) -> Self {
+        Self {
+            filename: filename.into(),
+            content_type: content_type.into(),
+            content: content.into(),
+        }
+    }
+
+    /// Read the entire content of the file from the reader, such as a
+    /// `std::fs::File`.
+    pub fn read(
+        filename: impl Into<String>,
+        content_type: impl Into<String>,
+        mut reader: impl Read,
+    ) -> std::io::Result<Self> {
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content)?;
+        Ok(Self::new(filename, content_type, content))
+    }
+
+    /// Read the entire content of the file from an async reader, such as a
+    /// `tokio::fs::File`.
+    #[cfg(feature = "tokio")]
+    pub async fn read_async(
+        filename: impl Into<String>,
+        content_type: impl Into<String>,
+        mut reader: impl tokio::io::AsyncRead + Unpin,
+    ) -> std::io::Result<Self> {
+        use tokio::io::AsyncReadExt;
+
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content).await?;
+        Ok(Self::new(filename, content_type, content))
+    }
+}
+
+/// Does not show the content, which may be large.
+impl std::fmt::Debug for FilePart {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FilePart")
+            .field("filename", &self.filename)
+            .field("content_type", &self.content_type)
+            .field("len", &self.content.len())
+            .finish()
+    }
+}
+
+/// ```
+/// use http_typed::{FilePart, Multipart, MultipartForm, SerializeBody, ToMultipart};
+///
+/// struct Upload {
+///     file: FilePart,
+/// }
+///
+/// impl ToMultipart for Upload {
+///     fn to_multipart(&self) -> MultipartForm<'_> {
+///         MultipartForm::new().text("title", "Notes").file("file", &self.file)
+///     }
+/// }
+///
+/// let upload = Upload {
+///     file: FilePart::new("notes.txt", "text/plain", "hello"),
+/// };
+/// let body = Multipart::serialize_body(&upload)?;
+/// assert_eq!(
+///     std::str::from_utf8(&body).unwrap(),
+///     "--http-typed-boundary-0\r\n\
+///      Content-Disposition: form-data; name=\"title\"\r\n\
+///      \r\n\
+///      Notes\r\n\
+///      --http-typed-boundary-0\r\n\
+///      Content-Disposition: form-data; name=\"file\"; filename=\"notes.txt\"\r\n\
+///      Content-Type: text/plain\r\n\
+///      \r\n\
+///      hello\r\n\
+///      --http-typed-boundary-0--\r\n"
+/// );
+/// assert_eq!(
+///     Multipart::content_type(&upload).unwrap(),
+///     "multipart/form-data; boundary=http-typed-boundary-0"
+/// );
+/// # Ok::<_, http_typed::InvalidContentType>(())
+/// ```
+impl<T: ToMultipart> SerializeBody<T> for Multipart {
+    type Error = InvalidContentType;
+
+    fn serialize_body(request: &T) -> Result<Vec<u8>, Self::Error> {
+        let (body, _) = request.to_multipart().encode()?;
+        Ok(body)
+    }
+
+    fn content_type(request: &T) -> Option<String> {
+        Some(request.to_multipart().content_type())
+    }
+
+    fn serialize_body_and_content_type(
+        request: &T,
+    ) -> Result<(Vec<u8>, Option<String>), Self::Error> {
+        let (body, content_type) = request.to_multipart().encode()?;
+        Ok((body, Some(content_type)))
+    }
+}
+
+fn content_type(boundary: &str) -> String {
+    format!("multipart/form-data; boundary={boundary}")
+}
+
+/// Percent-encode the characters that would end a quoted header value or the
+/// header itself, as browsers do in form field names and filenames.
+fn escape(value: &str) -> Cow<'_, str> {
+    if !value.contains(['"', '\r', '\n']) {
+        return Cow::Borrowed(value);
+    }
+    Cow::Owned(
+        value
+            .replace('"', "%22")
+            .replace('\r', "%0D")
+            .replace('\n', "%0A"),
+    )
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(form: MultipartForm<'_>) -> String {
+        let (body, content_type) = form.encode().unwrap();
+        assert_eq!(content_type, form.content_type());
+        String::from_utf8(body).unwrap()
+    }
+
+    #[test]
+    fn empty_form() {
+        let form = MultipartForm::new();
+        assert_eq!(
+            form.content_type(),
+            "multipart/form-data; boundary=http-typed-boundary-0"
+        );
+        assert_eq!(encode(form), "--http-typed-boundary-0--\r\n");
+    }
+
+    #[test]
+    fn boundary_is_not_in_the_values() {
+        let file = FilePart::new("a.txt", "text/plain", "--http-typed-boundary-1--");
+        let form = MultipartForm::new()
+            .text("a", "http-typed-boundary-0")
+            .file("b", &file);
+        assert_eq!(form.boundary(), "http-typed-boundary-2");
+        assert_eq!(
+            encode(form),
+            "--http-typed-boundary-2\r\n\
+             Content-Disposition: form-data; name=\"a\"\r\n\
+             \r\n\
+             http-typed-boundary-0\r\n\
+             --http-typed-boundary-2\r\n\
+             Content-Disposition: form-data; name=\"b\"; filename=\"a.txt\"\r\n\
+             Content-Type: text/plain\r\n\
+             \r\n\
+             --http-typed-boundary-1--\r\n\
+             --http-typed-boundary-2--\r\n"
+        );
+    }
+
+    #[test]
+    fn names_and_filenames_are_escaped() {
+        let file = FilePart::new("x\"\r\ny.txt", "text/plain", "");
+        let form = MultipartForm::new()
+            .text("a\"b", "\"quoted\"\r\n")
+            .file("c\nd", &file);
+        assert_eq!(
+            encode(form),
+            "--http-typed-boundary-0\r\n\
+             Content-Disposition: form-data; name=\"a%22b\"\r\n\
+             \r\n\
+             \"quoted\"\r\n\r\n\
+             --http-typed-boundary-0\r\n\
+             Content-Disposition: form-data; name=\"c%0Ad\"; filename=\"x%22%0D%0Ay.txt\"\r\n\
+             Content-Type: text/plain\r\n\
+             \r\n\
+             \r\n\
+             --http-typed-boundary-0--\r\n"
+        );
+    }
+
+    #[test]
+    fn invalid_content_type_is_an_error() {
+        for content_type in ["text/plain\r\nX-Injected: 1", "text/plain\n", "a\"b"] {
+            let file = FilePart::new("a.txt", content_type, "");
+            let form = MultipartForm::new().file("file", &file);
+            assert_eq!(
+                form.encode(),
+                Err(InvalidContentType {
+                    name: "file".into(),
+                    content_type: content_type.into(),
+                })
+            );
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn read_async() {
+        let file = FilePart::read_async("a.txt", "text/plain", &b"hello"[..])
+            .await
+            .unwrap();
+        assert_eq!(file, FilePart::new("a.txt", "text/plain", "hello"));
+    }
+}
@@ -0,0 +1,431 @@
+use std::time::{Duration, Instant};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use tokio::sync::Mutex;
+
+use crate::{
+    middleware::BoxError, Client, FormUrlEncoded, HttpMethod, Request, SerdeJson, TokenProvider,
+    UrlError,
+};
+
+/// A `TokenProvider` for the OAuth2 client credentials grant, described in
+/// RFC 6749 section 4.4. Tokens are fetched from the token endpoint with a
+/// `ClientCredentialsRequest`, and cached until shortly before they expire.
+///
+/// ```ignore
+/// let provider = ClientCredentials::new("https://auth.example.com/token", "id", "secret")?
+///     .scope("read write");
+/// let client = Client::<MyApi>::new("https://api.example.com".into())?
+///     .auth(Auth::provider(provider));
+/// ```
+///
+/// When many requests are sent at once, only one of them fetches a new token,
+/// and the others wait for it. If a request is rejected with status 401, the
+/// token is discarded and the request is sent once more with a new token.
+pub struct ClientCredentials {
+    client: Client,
+    token_url: String,
+    client_id: String,
+    client_secret: String,
+    scope: Option<String>,
+    credentials_in_body: bool,
+    refresh_before: Duration,
+    token: Mutex<Option<CachedToken>>,
+}
+
+/// The request sent to the token endpoint to get an access token.
+#[derive(Clone, serde::Serialize)]
+pub struct ClientCredentialsRequest {
+    grant_type: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_secret: Option<String>,
+    /// Credentials for the Authorization header, unless they are in the body.
+    #[serde(skip)]
+    basic: Option<String>,
+}
+
+/// Successful response from the token endpoint.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    /// Lifetime of the token in seconds.
+    pub expires_in: Option<u64>,
+    pub scope: Option<String>,
+}
+
+/// Error response from the token endpoint.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct TokenErrorResponse {
+    pub error: String,
+    pub error_description: Option<String>,
+    pub error_uri: Option<String>,
+}
+
+struct CachedToken {
+    access_token: String,
+    expires_at: Option<Instant>,
+    /// The token this one replaced, which requests sent before the fetch may
+    /// still be rejected with.
+    replaced: Option<String>,
+}
+
+impl ClientCredentials {
+    /// Fetch tokens from the token_url, authenticating with the client id and
+    /// secret in the Authorization header.
+    pub fn new(
+        token_url: impl Into<String>,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Result<Self, UrlError> {
+        let client = Client::default();
+        let token_url = token_url.into();
+        client.url(&token_url, "")?;
+        Ok(Self {
+            client,
+            token_url,
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            scope: None,
+            credentials_in_body: false,
+            refresh_before: Duration::from_secs(30),
+            token: Mutex::new(None),
+        })
+    }
+
+    /// Space separated scopes to request.
+    pub fn scope(mut self, scope: impl Into<String>) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+
+    /// Send the client id and secret in the request body instead of the
+    /// Authorization header, for servers that do not support HTTP Basic
+    /// authentication.
+    pub fn credentials_in_body(mut self) -> Self {
+        self.credentials_in_body = true;
+        self
+    }
+
+    /// How long before a token expires that a new one is fetched. Defaults to
+    /// 30 seconds.
+    pub fn refresh_before(mut self, refresh_before: Duration) -> Self {
+        self.refresh_before = refresh_before;
+        self
+    }
+
+    /// Send requests to the token endpoint with this client, for example to
+    /// configure timeouts or middleware. The client's base url, if any, is
+    /// joined with the token_url as in `Client::send_to`.
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Fetch a new token from the token endpoint, ignoring the cached token.
+    pub async fn fetch(&self) -> Result<TokenResponse, BoxError> {
+        Ok(self.client.send_to(&self.token_url, self.request()).await?)
+    }
+
+    fn request(&self) -> ClientCredentialsRequest {
+        let mut request = ClientCredentialsRequest {
+            grant_type: "client_credentials",
+            scope: self.scope.clone(),
+            client_id: None,
+            client_secret: None,
+            basic: None,
+        };
+        if self.credentials_in_body {
+            request.client_id = Some(self.client_id.clone());
+            request.client_secret = Some(self.client_secret.clone());
+        } else {
+            // RFC 6749 section 2.3.1 requires the id and secret to be encoded
+            let credentials = format!(
+                "{}:{}",
+                utf8_percent_encode(&self.client_id, NON_ALPHANUMERIC),
+                utf8_percent_encode(&self.client_secret, NON_ALPHANUMERIC)
+            );
+            request.basic = Some(STANDARD.encode(credentials));
+        }
+        request
+    }
+}
+
+/// Explicitly implemented to avoid showing the client secret
+impl std::fmt::Debug for ClientCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientCredentials")
+            .field("client", &self.client)
+            .field("token_url", &self.token_url)
+            .field("client_id", &self.client_id)
+            .field("scope", &self.scope)
+            .field("credentials_in_body", &self.credentials_in_body)
+            .field("refresh_before", &self.refresh_before)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait::async_trait]
+impl TokenProvider for ClientCredentials {
+    async fn token(&self) -> Result<String, BoxError> {
+        let mut cached = self.token.lock().await;
+        if let Some(token) = cached.as_ref() {
+            let fresh = match token.expires_at {
+                Some(expires_at) => Instant::now() + self.refresh_before < expires_at,
+                None => true,
+            };
+            if fresh {
+                return Ok(token.access_token.clone());
+            }
+        }
+        let requested_at = Instant::now();
+        let response = self.fetch().await?;
+        *cached = Some(CachedToken {
+            access_token: response.access_token.clone(),
+            expires_at: response
+                .expires_in
+                .map(|expires_in| requested_at + Duration::from_secs(expires_in)),
+            replaced: cached.take().map(|token| token.access_token),
+        });
+        Ok(response.access_token)
+    }
+
+    /// Returns true if the token is the cached one, which is discarded, or if
+    /// it is the one the cached token replaced, and false for any other
+    /// token.
+    async fn invalidate(&self, token: &str) -> bool {
+        let mut cached = self.token.lock().await;
+        match cached.as_mut() {
+            Some(cached) if cached.access_token == token => {
+                // keep the token to recognize requests that were sent with it
+                // at the same time, but fetch a new one on the next request
+                cached.expires_at = Some(Instant::now());
+                true
+            }
+            Some(cached) => cached.replaced.as_deref() == Some(token),
+            None => false,
+        }
+    }
+}
+
+impl Request for ClientCredentialsRequest {
+    type Serializer = FormUrlEncoded;
+    type Deserializer = SerdeJson;
+    type Response = TokenResponse;
+    type ErrorResponse = TokenErrorResponse;
+
+    fn method(&self) -> HttpMethod {
+        HttpMethod::Post
+    }
+
+    fn path(&self) -> String {
+        String::new()
+    }
+
+    fn headers(&self) -> Vec<(String, String)> {
+        match &self.basic {
+            Some(basic) => vec![("Authorization".to_owned(), format!("Basic {basic}"))],
+            None => vec![],
+        }
+    }
+
+    fn authenticate(&self) -> bool {
+        false
+    }
+}
+
+/// Explicitly implemented to avoid showing the client secret
+impl std::fmt::Debug for ClientCredentialsRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientCredentialsRequest")
+            .field("grant_type", &self.grant_type)
+            .field("scope", &self.scope)
+            .field("client_id", &self.client_id)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+    use crate::{testing::Ping, All, Auth};
+
+    /// The requests received by the token endpoint, as the request line and
+    /// headers, and the body.
+    type Received = Arc<Mutex<Vec<(String, String)>>>;
+
+    /// Serves `POST /token`, responding with the tokens `t0`, `t1`, ... after
+    /// the delay, and `GET /ping`, which only accepts `t1`. Returns the base
+    /// url.
+    fn serve(expires_in: Option<u64>, delay: Duration) -> (String, Received) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let received = Received::default();
+        let tokens = received.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut head = String::new();
+                while reader.read_line(&mut head).unwrap() > 2 {}
+                let length =
+                    header(&head, "content-length").map_or(0, |length| length.parse().unwrap());
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                let (status, body) = if head.starts_with("POST /token ") {
+                    let mut tokens = tokens.lock().unwrap();
+                    let token = format!("t{}", tokens.len());
+                    tokens.push((head, String::from_utf8(body).unwrap()));
+                    drop(tokens);
+                    std::thread::sleep(delay);
+                    let body = serde_json::json!({
+                        "access_token": token,
+                        "token_type": "Bearer",
+                        "expires_in": expires_in,
+                    });
+                    (200, body.to_string())
+                } else if header(&head, "authorization") == Some("Bearer t1") {
+                    (200, "pong".to_owned())
+                } else {
+                    (401, "expired".to_owned())
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {status} -\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+        (url, received)
+    }
+
+    fn header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+        head.lines().find_map(|line| {
+            let (header, value) = line.split_once(':')?;
+            header.eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+
+    fn provider(url: &str) -> ClientCredentials {
+        ClientCredentials::new(format!("{url}/token"), "my id", "s3cr:t").unwrap()
+    }
+
+    fn fetches(received: &Received) -> usize {
+        received.lock().unwrap().len()
+    }
+
+    #[tokio::test]
+    async fn credentials_in_basic_header() {
+        let (url, received) = serve(None, Duration::ZERO);
+        let token = provider(&url).scope("read write").token().await.unwrap();
+        assert_eq!(token, "t0");
+        let (head, body) = received.lock().unwrap()[0].clone();
+        assert_eq!(
+            header(&head, "content-type"),
+            Some("application/x-www-form-urlencoded")
+        );
+        assert_eq!(body, "grant_type=client_credentials&scope=read+write");
+        let basic = header(&head, "authorization").unwrap();
+        let basic = basic.strip_prefix("Basic ").unwrap();
+        let basic = String::from_utf8(STANDARD.decode(basic).unwrap()).unwrap();
+        assert_eq!(basic, "my%20id:s3cr%3At");
+        // servers form-decode the id and secret, which accepts %20 for a space
+        let (id, secret) = basic.split_once(':').unwrap();
+        let form: Vec<(String, String)> =
+            serde_urlencoded::from_str(&format!("id={id}&secret={secret}")).unwrap();
+        assert_eq!(form[0].1, "my id");
+        assert_eq!(form[1].1, "s3cr:t");
+    }
+
+    #[tokio::test]
+    async fn credentials_in_body() {
+        let (url, received) = serve(None, Duration::ZERO);
+        provider(&url).credentials_in_body().token().await.unwrap();
+        let (head, body) = received.lock().unwrap()[0].clone();
+        assert_eq!(header(&head, "authorization"), None);
+        assert_eq!(
+            body,
+            "grant_type=client_credentials&client_id=my+id&client_secret=s3cr%3At"
+        );
+    }
+
+    #[tokio::test]
+    async fn token_is_cached_until_it_expires() {
+        let (url, received) = serve(Some(3600), Duration::ZERO);
+        let provider = provider(&url);
+        assert_eq!(provider.token().await.unwrap(), "t0");
+        assert_eq!(provider.token().await.unwrap(), "t0");
+        assert_eq!(fetches(&received), 1);
+
+        // expires within refresh_before
+        let (url, received) = serve(Some(10), Duration::ZERO);
+        let provider = self::provider(&url).refresh_before(Duration::from_secs(10));
+        assert_eq!(provider.token().await.unwrap(), "t0");
+        assert_eq!(provider.token().await.unwrap(), "t1");
+        assert_eq!(fetches(&received), 2);
+    }
+
+    #[tokio::test]
+    async fn invalidated_token_is_replaced() {
+        let (url, received) = serve(None, Duration::ZERO);
+        let provider = provider(&url);
+        assert!(!provider.invalidate("t0").await);
+        assert_eq!(provider.token().await.unwrap(), "t0");
+        assert!(provider.invalidate("t0").await);
+        // a request sent with the same token at the same time
+        assert!(provider.invalidate("t0").await);
+        assert_eq!(provider.token().await.unwrap(), "t1");
+        assert!(provider.invalidate("t0").await);
+        assert_eq!(provider.token().await.unwrap(), "t1");
+        assert_eq!(fetches(&received), 2);
+    }
+
+    #[tokio::test]
+    async fn unknown_token_is_not_invalidated() {
+        let (url, received) = serve(None, Duration::ZERO);
+        let provider = provider(&url);
+        assert_eq!(provider.token().await.unwrap(), "t0");
+        assert!(!provider.invalidate("other").await);
+        assert_eq!(provider.token().await.unwrap(), "t0");
+        assert!(provider.invalidate("t0").await);
+        assert_eq!(provider.token().await.unwrap(), "t1");
+        assert!(!provider.invalidate("other").await);
+        assert!(provider.invalidate("t1").await);
+        assert_eq!(provider.token().await.unwrap(), "t2");
+        // only the token that t2 replaced is remembered
+        assert!(provider.invalidate("t1").await);
+        assert!(!provider.invalidate("t0").await);
+        assert_eq!(fetches(&received), 3);
+    }
+
+    #[tokio::test]
+    async fn rejected_token_is_refreshed() {
+        let (url, received) = serve(None, Duration::ZERO);
+        let client = Client::<All>::new(url.clone())
+            .unwrap()
+            .auth(Auth::provider(provider(&url)));
+        assert_eq!(client.send(Ping).await.unwrap(), "pong");
+        assert_eq!(fetches(&received), 2);
+    }
+
+    #[tokio::test]
+    async fn concurrent_callers_share_one_fetch() {
+        let (url, received) = serve(None, Duration::from_millis(100));
+        let provider = provider(&url);
+        let (a, b, c) = tokio::join!(provider.token(), provider.token(), provider.token());
+        assert_eq!([a.unwrap(), b.unwrap(), c.unwrap()], ["t0", "t0", "t0"]);
+        assert_eq!(fetches(&received), 1);
+    }
+}
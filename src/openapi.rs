@@ -0,0 +1,616 @@
+use schemars::{generate::SchemaSettings, JsonSchema, Schema, SchemaGenerator};
+use serde_json::{json, Map, Value};
+
+#[cfg(feature = "msgpack")]
+use crate::MessagePack;
+use crate::{
+    FilePart, FormUrlEncoded, GroupRequests, HttpMethod, Multipart, NoBody, PlainText, RawBytes,
+    Route, SerdeJson, SerdeQuery,
+};
+
+/// Builds an OpenAPI 3.0 document describing requests, using their `Route`
+/// implementations for the method and path, and `schemars` to generate the
+/// schemas of the request, response, and error response types.
+///
+/// ```ignore
+/// let document = OpenApi::new("My API", "1.0.0")
+///     .server("https://api.example.com")
+///     .group::<MyApi>()
+///     .to_json();
+/// std::fs::write("openapi.json", serde_json::to_string_pretty(&document)?)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct OpenApi {
+    title: String,
+    version: String,
+    servers: Vec<String>,
+    paths: Map<String, Value>,
+    generator: SchemaGenerator,
+}
+
+/// Describes how a serializer or deserializer transmits a request or response
+/// in an OpenAPI document.
+pub trait OpenApiFormat {
+    /// Media type of the body, or None if there is no body.
+    const MEDIA_TYPE: Option<&'static str>;
+
+    /// Whether the request is sent in the query string instead of the body.
+    const QUERY: bool = false;
+
+    /// Adjust the schema generated for the body, for formats that are not
+    /// represented by their serde data model.
+    fn body_schema(schema: Schema) -> Schema {
+        schema
+    }
+}
+
+/// Implemented for the `Requests` list of a request group when every request
+/// in it can be described by `OpenApi::request`. It is not implemented for an
+/// empty list, so that a group whose requests were all added with
+/// `#[request(group = ...)]` is not silently described as empty.
+#[diagnostic::on_unimplemented(
+    message = "the requests of this group cannot be described by OpenApi",
+    note = "`OpenApi::group` only describes the requests listed in `request_group!`, and the list must not be empty",
+    note = "requests added to a group with `#[request(group = ...)]` must be described with `OpenApi::request`"
+)]
+pub trait DescribeRequests {
+    fn describe(openapi: OpenApi) -> OpenApi;
+}
+
+impl OpenApi {
+    pub fn new(title: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            version: version.into(),
+            servers: vec![],
+            paths: Map::new(),
+            generator: SchemaSettings::openapi3().into_generator(),
+        }
+    }
+
+    /// Add a base url that the requests can be sent to.
+    pub fn server(mut self, url: impl Into<String>) -> Self {
+        self.servers.push(url.into());
+        self
+    }
+
+    /// Describe every request listed in the group's request_group macro.
+    /// Requests added to the group with `#[request(group = ...)]` are not
+    /// listed, so describe them with `request` instead. A group without any
+    /// listed requests does not compile.
+    pub fn group<Group>(self) -> Self
+    where
+        Group: GroupRequests,
+        Group::Requests: DescribeRequests,
+    {
+        Group::Requests::describe(self)
+    }
+
+    /// Describe a single request. Its path parameters and, for `SerdeQuery`,
+    /// its query parameters are taken from the properties of its schema.
+    pub fn request<Req>(mut self) -> Self
+    where
+        Req: Route + JsonSchema,
+        Req::Response: JsonSchema,
+        Req::ErrorResponse: JsonSchema,
+        Req::Serializer: OpenApiFormat,
+        Req::Deserializer: OpenApiFormat,
+    {
+        let request_schema = self.generator.subschema_for::<Req>();
+        let properties = self.properties(&request_schema);
+        let path_params = path_params(Req::PATH);
+
+        let mut parameters = vec![];
+        for name in &path_params {
+            let schema = properties
+                .iter()
+                .find(|(property, ..)| property == name)
+                .map(|(_, schema, _)| schema.clone())
+                .unwrap_or_else(|| schemars::json_schema!({ "type": "string" }));
+            parameters.push(json!({
+                "name": name,
+                "in": "path",
+                "required": true,
+                "schema": schema,
+            }));
+        }
+        if <Req::Serializer as OpenApiFormat>::QUERY {
+            for (name, schema, required) in &properties {
+                if !path_params.contains(&name.as_str()) {
+                    parameters.push(json!({
+                        "name": name,
+                        "in": "query",
+                        "required": required,
+                        "schema": schema,
+                    }));
+                }
+            }
+        }
+
+        let mut operation = Map::new();
+        operation.insert("operationId".into(), Req::schema_name().into());
+        if !parameters.is_empty() {
+            operation.insert("parameters".into(), parameters.into());
+        }
+        if let Some(media_type) = <Req::Serializer as OpenApiFormat>::MEDIA_TYPE {
+            let schema = self.transformed(Req::Serializer::body_schema(request_schema));
+            operation.insert(
+                "requestBody".into(),
+                json!({
+                    "required": true,
+                    "content": { media_type: { "schema": schema } },
+                }),
+            );
+        }
+        let mut success = json!({ "description": "Success" });
+        if let Some(media_type) = <Req::Deserializer as OpenApiFormat>::MEDIA_TYPE {
+            let schema = self.generator.subschema_for::<Req::Response>();
+            let schema = self.transformed(Req::Deserializer::body_schema(schema));
+            success["content"] = json!({ media_type: { "schema": schema } });
+        }
+        let error_schema = self.generator.subschema_for::<Req::ErrorResponse>();
+        let error_schema = self.transformed(error_schema);
+        // the server sends error bodies that serialize to a string as text
+        let error_media_type = match error_schema.get("type").and_then(Value::as_str) {
+            Some("string") => "text/plain",
+            _ => "application/json",
+        };
+        operation.insert(
+            "responses".into(),
+            json!({
+                "2XX": success,
+                "default": {
+                    "description": "Error",
+                    "content": { error_media_type: { "schema": error_schema } },
+                },
+            }),
+        );
+
+        // OpenAPI 3.0 cannot describe CONNECT requests
+        if let Some(method) = method_name(Req::METHOD) {
+            let path = self
+                .paths
+                .entry(Req::PATH)
+                .or_insert_with(|| Value::Object(Map::new()));
+            path[method] = operation.into();
+        }
+        self
+    }
+
+    /// The OpenAPI document as json.
+    pub fn to_json(&self) -> Value {
+        let schemas = self.generator.clone().take_definitions(true);
+        let mut document = json!({
+            "openapi": "3.0.3",
+            "info": { "title": self.title, "version": self.version },
+            "paths": self.paths,
+            "components": { "schemas": schemas },
+        });
+        if !self.servers.is_empty() {
+            document["servers"] = self
+                .servers
+                .iter()
+                .map(|url| json!({ "url": url }))
+                .collect();
+        }
+        document
+    }
+
+    /// The properties of an object schema, or of the schema it references,
+    /// along with whether each one is required.
+    fn properties(&mut self, schema: &Schema) -> Vec<(String, Schema, bool)> {
+        let schema = match schema.get("$ref").and_then(Value::as_str) {
+            Some(reference) => reference
+                .rsplit('/')
+                .next()
+                .and_then(|name| self.generator.definitions().get(name)),
+            None => Some(schema.as_value()),
+        };
+        let Some(schema) = schema else {
+            return vec![];
+        };
+        let required: Vec<&str> = schema["required"]
+            .as_array()
+            .map(|required| required.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        let Some(properties) = schema["properties"].as_object() else {
+            return vec![];
+        };
+        let properties: Vec<_> = properties
+            .iter()
+            .filter_map(|(name, schema)| {
+                let required = required.contains(&name.as_str());
+                Some((
+                    name.clone(),
+                    Schema::try_from(schema.clone()).ok()?,
+                    required,
+                ))
+            })
+            .collect();
+        properties
+            .into_iter()
+            .map(|(name, schema, required)| (name, self.transformed(schema), required))
+            .collect()
+    }
+
+    fn transformed(&mut self, mut schema: Schema) -> Schema {
+        for transform in self.generator.transforms_mut() {
+            transform.transform(&mut schema);
+        }
+        schema
+    }
+}
+
+impl<Req> DescribeRequests for (Req, ())
+where
+    Req: Route + JsonSchema,
+    Req::Response: JsonSchema,
+    Req::ErrorResponse: JsonSchema,
+    Req::Serializer: OpenApiFormat,
+    Req::Deserializer: OpenApiFormat,
+{
+    fn describe(openapi: OpenApi) -> OpenApi {
+        openapi.request::<Req>()
+    }
+}
+
+impl<Req, Next, Rest> DescribeRequests for (Req, (Next, Rest))
+where
+    Req: Route + JsonSchema,
+    Req::Response: JsonSchema,
+    Req::ErrorResponse: JsonSchema,
+    Req::Serializer: OpenApiFormat,
+    Req::Deserializer: OpenApiFormat,
+    (Next, Rest): DescribeRequests,
+{
+    fn describe(openapi: OpenApi) -> OpenApi {
+        <(Next, Rest)>::describe(openapi.request::<Req>())
+    }
+}
+
+impl OpenApiFormat for SerdeJson {
+    const MEDIA_TYPE: Option<&'static str> = Some("application/json");
+}
+
+impl OpenApiFormat for NoBody {
+    const MEDIA_TYPE: Option<&'static str> = None;
+}
+
+impl OpenApiFormat for SerdeQuery {
+    const MEDIA_TYPE: Option<&'static str> = None;
+    const QUERY: bool = true;
+}
+
+impl OpenApiFormat for FormUrlEncoded {
+    const MEDIA_TYPE: Option<&'static str> = Some("application/x-www-form-urlencoded");
+}
+
+impl OpenApiFormat for PlainText {
+    const MEDIA_TYPE: Option<&'static str> = Some("text/plain");
+}
+
+impl OpenApiFormat for RawBytes {
+    const MEDIA_TYPE: Option<&'static str> = Some("application/octet-stream");
+
+    fn body_schema(_: Schema) -> Schema {
+        schemars::json_schema!({ "type": "string", "format": "binary" })
+    }
+}
+
+/// The fields of the request are described as the properties of the form.
+impl OpenApiFormat for Multipart {
+    const MEDIA_TYPE: Option<&'static str> = Some("multipart/form-data");
+}
+
+/// Described as a binary string, which is how OpenAPI describes a file in a
+/// `multipart/form-data` body.
+impl JsonSchema for FilePart {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "FilePart".into()
+    }
+
+    fn inline_schema() -> bool {
+        true
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        schemars::json_schema!({ "type": "string", "format": "binary" })
+    }
+}
+
+#[cfg(feature = "msgpack")]
+impl OpenApiFormat for MessagePack {
+    const MEDIA_TYPE: Option<&'static str> = Some("application/msgpack");
+}
+
+/// The names of the `{field}` parameters in a path template.
+fn path_params(path: &str) -> Vec<&str> {
+    path.split('{')
+        .skip(1)
+        .filter_map(|rest| rest.split_once('}'))
+        .map(|(name, _)| name)
+        .collect()
+}
+
+fn method_name(method: HttpMethod) -> Option<&'static str> {
+    Some(match method {
+        HttpMethod::Options => "options",
+        HttpMethod::Get => "get",
+        HttpMethod::Post => "post",
+        HttpMethod::Put => "put",
+        HttpMethod::Delete => "delete",
+        HttpMethod::Head => "head",
+        HttpMethod::Trace => "trace",
+        HttpMethod::Patch => "patch",
+        HttpMethod::Connect => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use schemars::JsonSchema;
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{MultipartForm, Request, ToMultipart};
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    struct User {
+        id: String,
+        name: String,
+    }
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    struct Problem {
+        code: u16,
+        message: String,
+    }
+
+    #[derive(Serialize, JsonSchema)]
+    struct GetUser {
+        id: String,
+    }
+
+    impl Request for GetUser {
+        type Serializer = NoBody;
+        type Deserializer = SerdeJson;
+        type Response = User;
+        type ErrorResponse = String;
+
+        fn method(&self) -> HttpMethod {
+            HttpMethod::Get
+        }
+
+        fn path(&self) -> String {
+            format!("/users/{}", self.id)
+        }
+    }
+
+    impl Route for GetUser {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/users/{id}";
+    }
+
+    #[derive(Serialize, JsonSchema)]
+    struct ListPosts {
+        user: u32,
+        limit: Option<u32>,
+    }
+
+    impl Request for ListPosts {
+        type Serializer = SerdeQuery;
+        type Deserializer = SerdeJson;
+        type Response = Vec<String>;
+        type ErrorResponse = String;
+
+        fn method(&self) -> HttpMethod {
+            HttpMethod::Get
+        }
+
+        fn path(&self) -> String {
+            format!("/users/{}/posts", self.user)
+        }
+    }
+
+    impl Route for ListPosts {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/users/{user}/posts";
+    }
+
+    #[derive(Serialize, JsonSchema)]
+    struct CreateUser {
+        name: String,
+    }
+
+    impl Request for CreateUser {
+        type Serializer = SerdeJson;
+        type Deserializer = SerdeJson;
+        type Response = User;
+        type ErrorResponse = Problem;
+
+        fn method(&self) -> HttpMethod {
+            HttpMethod::Post
+        }
+
+        fn path(&self) -> String {
+            "/users".into()
+        }
+    }
+
+    impl Route for CreateUser {
+        const METHOD: HttpMethod = HttpMethod::Post;
+        const PATH: &'static str = "/users";
+    }
+
+    #[derive(Serialize, JsonSchema)]
+    struct Tunnel;
+
+    impl Request for Tunnel {
+        type Serializer = NoBody;
+        type Deserializer = NoBody;
+        type Response = ();
+        type ErrorResponse = String;
+
+        fn method(&self) -> HttpMethod {
+            HttpMethod::Connect
+        }
+
+        fn path(&self) -> String {
+            "/tunnel".into()
+        }
+    }
+
+    impl Route for Tunnel {
+        const METHOD: HttpMethod = HttpMethod::Connect;
+        const PATH: &'static str = "/tunnel";
+    }
+
+    #[derive(JsonSchema)]
+    struct UploadAvatar {
+        title: String,
+        image: FilePart,
+    }
+
+    impl Request for UploadAvatar {
+        type Serializer = Multipart;
+        type Deserializer = NoBody;
+        type Response = ();
+        type ErrorResponse = String;
+
+        fn method(&self) -> HttpMethod {
+            HttpMethod::Put
+        }
+
+        fn path(&self) -> String {
+            "/avatar".into()
+        }
+    }
+
+    impl Route for UploadAvatar {
+        const METHOD: HttpMethod = HttpMethod::Put;
+        const PATH: &'static str = "/avatar";
+    }
+
+    impl ToMultipart for UploadAvatar {
+        fn to_multipart(&self) -> MultipartForm<'_> {
+            MultipartForm::new()
+                .text("title", &self.title)
+                .file("image", &self.image)
+        }
+    }
+
+    fn document() -> Value {
+        OpenApi::new("Users", "1.0.0")
+            .request::<GetUser>()
+            .request::<ListPosts>()
+            .request::<CreateUser>()
+            .request::<UploadAvatar>()
+            .request::<Tunnel>()
+            .to_json()
+    }
+
+    /// The name, location, and whether it is required, of each parameter.
+    fn parameters(operation: &Value) -> Vec<(&str, &str, bool)> {
+        operation["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|parameter| {
+                (
+                    parameter["name"].as_str().unwrap(),
+                    parameter["in"].as_str().unwrap(),
+                    parameter["required"].as_bool().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn path_params() {
+        let document = document();
+        let operation = &document["paths"]["/users/{id}"]["get"];
+        assert_eq!(parameters(operation), [("id", "path", true)]);
+        assert_eq!(
+            operation["parameters"][0]["schema"],
+            json!({ "type": "string" })
+        );
+    }
+
+    #[test]
+    fn query_params_from_serde_query() {
+        let document = document();
+        let operation = &document["paths"]["/users/{user}/posts"]["get"];
+        assert_eq!(
+            parameters(operation),
+            [("user", "path", true), ("limit", "query", false)]
+        );
+        assert_eq!(operation["parameters"][0]["schema"]["type"], "integer");
+        assert!(operation.get("requestBody").is_none());
+    }
+
+    #[test]
+    fn request_body() {
+        let document = document();
+        let operation = &document["paths"]["/users"]["post"];
+        assert_eq!(
+            operation["requestBody"]["content"]["application/json"]["schema"],
+            json!({ "$ref": "#/components/schemas/CreateUser" })
+        );
+        assert!(operation.get("parameters").is_none());
+        let operation = &document["paths"]["/users/{id}"]["get"];
+        assert!(operation.get("requestBody").is_none());
+    }
+
+    #[test]
+    fn multipart_request_body() {
+        let document = document();
+        let content = &document["paths"]["/avatar"]["put"]["requestBody"]["content"];
+        assert_eq!(
+            content["multipart/form-data"]["schema"],
+            json!({ "$ref": "#/components/schemas/UploadAvatar" })
+        );
+        let schema = &document["components"]["schemas"]["UploadAvatar"];
+        assert_eq!(
+            schema["properties"]["image"],
+            json!({ "type": "string", "format": "binary" })
+        );
+    }
+
+    #[test]
+    fn responses() {
+        let document = document();
+        let responses = &document["paths"]["/users"]["post"]["responses"];
+        assert_eq!(
+            responses["2XX"]["content"]["application/json"]["schema"],
+            json!({ "$ref": "#/components/schemas/User" })
+        );
+        assert_eq!(
+            responses["default"]["content"]["application/json"]["schema"],
+            json!({ "$ref": "#/components/schemas/Problem" })
+        );
+        let schemas = &document["components"]["schemas"];
+        assert_eq!(schemas["User"]["required"], json!(["id", "name"]));
+        assert_eq!(schemas["Problem"]["required"], json!(["code", "message"]));
+
+        // string error bodies are sent as plain text
+        let responses = &document["paths"]["/users/{user}/posts"]["get"]["responses"];
+        assert_eq!(
+            responses["2XX"]["content"]["application/json"]["schema"]["items"],
+            json!({ "type": "string" })
+        );
+        assert_eq!(
+            responses["default"]["content"],
+            json!({ "text/plain": { "schema": { "type": "string" } } })
+        );
+    }
+
+    #[test]
+    fn connect_is_skipped() {
+        let document = document();
+        let paths: Vec<&String> = document["paths"].as_object().unwrap().keys().collect();
+        assert_eq!(
+            paths,
+            ["/avatar", "/users", "/users/{id}", "/users/{user}/posts"]
+        );
+    }
+}
@@ -0,0 +1,366 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use futures_timer::Delay;
+use http::{header::RETRY_AFTER, StatusCode};
+
+use crate::middleware::{BoxError, HttpRequest, HttpResponse, Next, SendError};
+
+/// Determines when and how often a Client retries a request that failed with a
+/// transient error. Only idempotent requests are retried (see
+/// `Request::idempotent`).
+///
+/// A request is retried when:
+/// - the transport reports a transient error, such as when the connection
+///   fails, times out, or is reset while sending the request
+/// - the response status is one of the retryable statuses (by default 429,
+///   502, 503 and 504)
+///
+/// The delay before each retry grows exponentially from `initial_backoff` up
+/// to `max_backoff`, with full jitter. A valid `Retry-After` header on the
+/// response is used as the delay instead, unless it is longer than
+/// `max_retry_after`, in which case the response is returned without retrying.
+///
+/// ```ignore
+/// let client = Client::<MyApi>::new("http://example.com".into())?
+///     .retry(RetryPolicy::new().max_attempts(5).max_elapsed(Duration::from_secs(30)));
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    max_elapsed: Option<Duration>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    jitter: bool,
+    retry_after: bool,
+    max_retry_after: Duration,
+    statuses: Vec<StatusCode>,
+    on_retry: Option<OnRetry>,
+}
+
+type OnRetry = Arc<dyn Fn(&RetryEvent) + Send + Sync>;
+
+/// Describes a retry that is about to happen.
+#[derive(Debug)]
+pub struct RetryEvent<'a> {
+    /// The attempt that failed, starting at 1 for the first attempt.
+    pub attempt: u32,
+    /// How long the client will wait before the next attempt.
+    pub delay: Duration,
+    pub reason: RetryReason<'a>,
+}
+
+#[derive(Debug)]
+pub enum RetryReason<'a> {
+    /// The response had a retryable status code.
+    Status(StatusCode),
+    /// The request could not be sent.
+    Error(&'a BoxError),
+}
+
+impl std::fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("max_elapsed", &self.max_elapsed)
+            .field("initial_backoff", &self.initial_backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("jitter", &self.jitter)
+            .field("retry_after", &self.retry_after)
+            .field("max_retry_after", &self.max_retry_after)
+            .field("statuses", &self.statuses)
+            .field("on_retry", &self.on_retry.is_some())
+            .finish()
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            max_elapsed: None,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            jitter: true,
+            retry_after: true,
+            max_retry_after: Duration::from_secs(60),
+            statuses: vec![
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            on_retry: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maximum number of times to send the request, including the first
+    /// attempt. Defaults to 3.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Stop retrying if the next attempt would start after this much time has
+    /// passed since the first attempt. Unlimited by default.
+    pub fn max_elapsed(mut self, max_elapsed: Duration) -> Self {
+        self.max_elapsed = Some(max_elapsed);
+        self
+    }
+
+    /// Delay before the first retry, which is doubled for each subsequent
+    /// retry. Defaults to 100ms.
+    pub fn initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    /// Upper limit for the exponential backoff. Defaults to 10s.
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Whether to pick a random delay between zero and the backoff, so that
+    /// many clients do not retry at the same time. Enabled by default.
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Whether to wait for the duration in the `Retry-After` response header
+    /// when it is present. Enabled by default.
+    pub fn retry_after(mut self, retry_after: bool) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    /// Longest `Retry-After` delay to wait for. A response asking for a longer
+    /// delay is returned without retrying, rather than blocking the caller.
+    /// Defaults to 60s.
+    pub fn max_retry_after(mut self, max_retry_after: Duration) -> Self {
+        self.max_retry_after = max_retry_after;
+        self
+    }
+
+    /// Response status codes that should be retried. Defaults to 429, 502, 503
+    /// and 504.
+    pub fn statuses(mut self, statuses: impl IntoIterator<Item = StatusCode>) -> Self {
+        self.statuses = statuses.into_iter().collect();
+        self
+    }
+
+    /// Called before waiting for each retry.
+    pub fn on_retry(mut self, on_retry: impl Fn(&RetryEvent) + Send + Sync + 'static) -> Self {
+        self.on_retry = Some(Arc::new(on_retry));
+        self
+    }
+
+    /// Send the request through the middleware chain, retrying as needed.
+    pub(crate) async fn run(
+        &self,
+        next: Next<'_>,
+        request: HttpRequest,
+    ) -> Result<HttpResponse, SendError> {
+        let start = Instant::now();
+        let mut attempt = 1;
+        loop {
+            let result = next.send(request.clone()).await;
+            if attempt >= self.max_attempts {
+                return result;
+            }
+            let (reason, delay) = match &result {
+                Ok(response) if self.statuses.contains(&response.status) => {
+                    let delay = match self.retry_after_delay(response) {
+                        Some(delay) if delay > self.max_retry_after => return result,
+                        Some(delay) => delay,
+                        None => self.backoff(attempt),
+                    };
+                    (RetryReason::Status(response.status), delay)
+                }
+                Err(SendError::Transport(error)) if next.transport().is_transient(error) => {
+                    (RetryReason::Error(error), self.backoff(attempt))
+                }
+                _ => return result,
+            };
+            if let Some(max_elapsed) = self.max_elapsed {
+                if start.elapsed() + delay > max_elapsed {
+                    return result;
+                }
+            }
+            if let Some(on_retry) = &self.on_retry {
+                on_retry(&RetryEvent {
+                    attempt,
+                    delay,
+                    reason,
+                });
+            }
+            Delay::new(delay).await;
+            attempt += 1;
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_backoff);
+        if self.jitter {
+            backoff.mul_f64(random_fraction())
+        } else {
+            backoff
+        }
+    }
+
+    /// Parses Retry-After as either a number of seconds or an http date.
+    fn retry_after_delay(&self, response: &HttpResponse) -> Option<Duration> {
+        if !self.retry_after {
+            return None;
+        }
+        let value = response.headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+        if let Ok(seconds) = value.parse::<u64>() {
+            return Some(Duration::from_secs(seconds));
+        }
+        let date = httpdate::parse_http_date(value).ok()?;
+        Some(
+            date.duration_since(SystemTime::now())
+                .unwrap_or(Duration::ZERO),
+        )
+    }
+}
+
+/// A number in [0, 1), good enough for jitter without depending on a random
+/// number generator.
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use http::HeaderValue;
+
+    use super::*;
+    use crate::{
+        testing::{response, Ping, Stub},
+        All, Client, Transport,
+    };
+
+    fn with_retry_after(value: &str) -> HttpResponse {
+        let mut response = response(503, b"");
+        response
+            .headers
+            .insert(RETRY_AFTER, HeaderValue::from_str(value).unwrap());
+        response
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let policy = RetryPolicy::new()
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_millis(500))
+            .jitter(false);
+        let delays: Vec<_> = (1..=5).map(|attempt| policy.backoff(attempt)).collect();
+        let expected = [100, 200, 400, 500, 500].map(Duration::from_millis);
+        assert_eq!(delays, expected);
+        assert_eq!(policy.backoff(200), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn jitter_is_below_backoff() {
+        let policy = RetryPolicy::new()
+            .initial_backoff(Duration::from_millis(100))
+            .max_backoff(Duration::from_secs(1));
+        for attempt in 1..=6 {
+            let max = Duration::from_millis(100 << (attempt - 1)).min(Duration::from_secs(1));
+            for _ in 0..50 {
+                assert!(policy.backoff(attempt) < max);
+            }
+        }
+        for _ in 0..1000 {
+            let fraction = random_fraction();
+            assert!((0.0..1.0).contains(&fraction));
+        }
+    }
+
+    #[test]
+    fn retry_after_seconds_and_date() {
+        let policy = RetryPolicy::new();
+        let delay = |response: &HttpResponse| policy.retry_after_delay(response);
+        assert_eq!(delay(&with_retry_after("7")), Some(Duration::from_secs(7)));
+        assert_eq!(delay(&with_retry_after(" 0 ")), Some(Duration::ZERO));
+        let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(120));
+        let from_date = delay(&with_retry_after(&date)).unwrap();
+        assert!(from_date > Duration::from_secs(110) && from_date <= Duration::from_secs(120));
+        let past = httpdate::fmt_http_date(SystemTime::now() - Duration::from_secs(120));
+        assert_eq!(delay(&with_retry_after(&past)), Some(Duration::ZERO));
+        assert_eq!(delay(&with_retry_after("soon")), None);
+        assert_eq!(delay(&with_retry_after("-1")), None);
+        assert_eq!(delay(&response(503, b"")), None);
+        let ignored = RetryPolicy::new().retry_after(false);
+        assert_eq!(ignored.retry_after_delay(&with_retry_after("7")), None);
+    }
+
+    async fn attempts(policy: RetryPolicy, response: HttpResponse) -> usize {
+        let stub = Arc::new(Stub::new(move |_| Ok(response.clone())));
+        let client = Client::<All, _>::with_inner("http://example.com".into(), stub.clone())
+            .unwrap()
+            .retry(policy);
+        let _ = client.send(Ping).await;
+        let attempts = stub.requests.lock().unwrap().len();
+        attempts
+    }
+
+    #[tokio::test]
+    async fn long_retry_after_is_not_waited_for() {
+        let policy = RetryPolicy::new().max_attempts(3);
+        assert_eq!(attempts(policy.clone(), with_retry_after("0")).await, 3);
+        assert_eq!(attempts(policy.clone(), with_retry_after("3600")).await, 1);
+        let policy = policy.max_retry_after(Duration::ZERO);
+        assert_eq!(attempts(policy, with_retry_after("1")).await, 1);
+    }
+
+    #[tokio::test]
+    async fn only_transient_transport_errors_are_retried() {
+        struct Flaky(Arc<Stub>);
+
+        #[async_trait::async_trait]
+        impl Transport for Flaky {
+            async fn send(&self, request: HttpRequest) -> Result<HttpResponse, BoxError> {
+                self.0.send(request).await
+            }
+
+            fn is_transient(&self, error: &BoxError) -> bool {
+                error.to_string() == "reset"
+            }
+        }
+
+        for (error, expected) in [("reset", 3), ("refused", 1)] {
+            let stub = Arc::new(Stub::new(move |_| Err(error.into())));
+            let client =
+                Client::<All, _>::with_inner("http://example.com".into(), Flaky(stub.clone()))
+                    .unwrap()
+                    .retry(RetryPolicy::new().initial_backoff(Duration::ZERO));
+            assert!(client.send(Ping).await.is_err());
+            assert_eq!(stub.requests.lock().unwrap().len(), expected);
+        }
+    }
+}
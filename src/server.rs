@@ -0,0 +1,635 @@
+use std::{convert::Infallible, fmt::Display};
+
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+
+#[cfg(feature = "msgpack")]
+use crate::MessagePack;
+use crate::{FormUrlEncoded, NoBody, PlainText, RawBytes, SerdeJson, SerdeQuery};
+
+/// The reverse of `SerializeBody`, used by servers to parse a request that was
+/// sent by a client. Implemented by the serializers that can be used as
+/// `Request::Serializer`.
+pub trait DeserializeRequest<T> {
+    type Error: Display;
+
+    /// Parse the request from the body, the query string without the leading
+    /// `?`, and the decoded values of the path parameters from `Route::PATH`.
+    fn deserialize_request(
+        body: &[u8],
+        query: Option<&str>,
+        path_params: &[(String, String)],
+    ) -> Result<T, Self::Error>;
+}
+
+/// The reverse of `DeserializeBody`, used by servers to write a response that
+/// a client can read. Implemented by the deserializers that can be used as
+/// `Request::Deserializer`.
+pub trait SerializeResponse<T> {
+    type Error: Display;
+
+    /// Content-Type of the response body, or None if there is no body.
+    const CONTENT_TYPE: Option<&'static str>;
+
+    fn serialize_response(response: &T) -> Result<Vec<u8>, Self::Error>;
+}
+
+/// Returned by a server handler to respond with a status code outside the 200
+/// range. A body that serializes to a json string, such as a `String`, is sent
+/// as plain text, and any other body is sent as json, which matches how the
+/// client deserializes `Request::ErrorResponse` before returning it as
+/// `Error::Api`.
+#[derive(Debug, Clone)]
+pub struct ApiError<E = String> {
+    pub status: u16,
+    pub body: E,
+}
+
+impl<E> ApiError<E> {
+    pub fn new(status: u16, body: E) -> Self {
+        Self { status, body }
+    }
+}
+
+/// The entire request is in the json body.
+impl<T> DeserializeRequest<T> for SerdeJson
+where
+    T: for<'a> serde::Deserialize<'a>,
+{
+    type Error = serde_json::error::Error;
+
+    fn deserialize_request(
+        body: &[u8],
+        _: Option<&str>,
+        _: &[(String, String)],
+    ) -> Result<T, Self::Error> {
+        serde_json::from_slice(body)
+    }
+}
+
+/// Only the path parameters are sent, so they must be the only fields of the
+/// request.
+impl<T> DeserializeRequest<T> for NoBody
+where
+    T: for<'a> serde::Deserialize<'a>,
+{
+    type Error = serde_urlencoded::de::Error;
+
+    fn deserialize_request(
+        _: &[u8],
+        _: Option<&str>,
+        path_params: &[(String, String)],
+    ) -> Result<T, Self::Error> {
+        serde_urlencoded::from_str(&form_encode(path_params))
+    }
+}
+
+/// The request is in the query string. Path parameters are included for any
+/// fields that are missing from the query string.
+impl<T> DeserializeRequest<T> for SerdeQuery
+where
+    T: for<'a> serde::Deserialize<'a>,
+{
+    type Error = serde_urlencoded::de::Error;
+
+    fn deserialize_request(
+        _: &[u8],
+        query: Option<&str>,
+        path_params: &[(String, String)],
+    ) -> Result<T, Self::Error> {
+        let query = query.unwrap_or_default();
+        let present: Vec<String> = serde_urlencoded::from_str::<Vec<(String, String)>>(query)?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        let missing: Vec<_> = path_params
+            .iter()
+            .filter(|(name, _)| !present.contains(name))
+            .cloned()
+            .collect();
+        let missing = form_encode(&missing);
+        match (query.is_empty(), missing.is_empty()) {
+            (_, true) => serde_urlencoded::from_str(query),
+            (true, false) => serde_urlencoded::from_str(&missing),
+            (false, false) => serde_urlencoded::from_str(&format!("{query}&{missing}")),
+        }
+    }
+}
+
+/// The entire request is in the form-urlencoded body.
+impl<T> DeserializeRequest<T> for FormUrlEncoded
+where
+    T: for<'a> serde::Deserialize<'a>,
+{
+    type Error = serde_urlencoded::de::Error;
+
+    fn deserialize_request(
+        body: &[u8],
+        _: Option<&str>,
+        _: &[(String, String)],
+    ) -> Result<T, Self::Error> {
+        serde_urlencoded::from_bytes(body)
+    }
+}
+
+#[cfg(feature = "msgpack")]
+impl<T> DeserializeRequest<T> for MessagePack
+where
+    T: for<'a> serde::Deserialize<'a>,
+{
+    type Error = rmp_serde::decode::Error;
+
+    fn deserialize_request(
+        body: &[u8],
+        _: Option<&str>,
+        _: &[(String, String)],
+    ) -> Result<T, Self::Error> {
+        rmp_serde::from_slice(body)
+    }
+}
+
+impl<T> SerializeResponse<T> for SerdeJson
+where
+    T: serde::Serialize,
+{
+    type Error = serde_json::error::Error;
+    const CONTENT_TYPE: Option<&'static str> = Some("application/json");
+
+    fn serialize_response(response: &T) -> Result<Vec<u8>, Self::Error> {
+        serde_json::to_vec(response)
+    }
+}
+
+/// Responds with an empty body.
+impl<T> SerializeResponse<T> for NoBody {
+    type Error = Infallible;
+    const CONTENT_TYPE: Option<&'static str> = None;
+
+    fn serialize_response(_: &T) -> Result<Vec<u8>, Self::Error> {
+        Ok(vec![])
+    }
+}
+
+impl SerializeResponse<String> for PlainText {
+    type Error = Infallible;
+    const CONTENT_TYPE: Option<&'static str> = Some("text/plain; charset=utf-8");
+
+    fn serialize_response(response: &String) -> Result<Vec<u8>, Self::Error> {
+        Ok(response.as_bytes().to_vec())
+    }
+}
+
+impl SerializeResponse<Vec<u8>> for RawBytes {
+    type Error = Infallible;
+    const CONTENT_TYPE: Option<&'static str> = Some("application/octet-stream");
+
+    fn serialize_response(response: &Vec<u8>) -> Result<Vec<u8>, Self::Error> {
+        Ok(response.clone())
+    }
+}
+
+#[cfg(feature = "msgpack")]
+impl<T> SerializeResponse<T> for MessagePack
+where
+    T: serde::Serialize,
+{
+    type Error = rmp_serde::encode::Error;
+    const CONTENT_TYPE: Option<&'static str> = Some("application/msgpack");
+
+    fn serialize_response(response: &T) -> Result<Vec<u8>, Self::Error> {
+        rmp_serde::to_vec_named(response)
+    }
+}
+
+fn form_encode(pairs: &[(String, String)]) -> String {
+    pairs
+        .iter()
+        .map(|(name, value)| {
+            format!(
+                "{}={}",
+                utf8_percent_encode(name, NON_ALPHANUMERIC),
+                utf8_percent_encode(value, NON_ALPHANUMERIC)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+#[cfg(feature = "axum")]
+pub use self::axum_routes::*;
+
+#[cfg(feature = "axum")]
+mod axum_routes {
+    use std::{any::type_name, future::Future, marker::PhantomData};
+
+    use axum::{
+        body::Bytes,
+        extract::{Path, RawQuery},
+        http::{header::CONTENT_TYPE, HeaderValue, StatusCode},
+        response::{IntoResponse, Response},
+        routing::{on, MethodFilter},
+        Router,
+    };
+
+    use super::{ApiError, DeserializeRequest, SerializeResponse};
+    use crate::{All, HttpMethod, InRequestGroup, Route};
+
+    /// Builds an axum Router from Request types. Each request type is routed
+    /// using its `Route` implementation, its body is parsed with
+    /// `DeserializeRequest`, and the handler's response is written with
+    /// `SerializeResponse`. Only requests in the RequestGroup can be added.
+    ///
+    /// ```ignore
+    /// let app: axum::Router = Routes::<MyApi>::new()
+    ///     .handle(|request: GetUser| async move {
+    ///         Ok(User { id: request.id, name: "name".into() })
+    ///     })
+    ///     .handle(|_: DeleteUser| async move {
+    ///         Err(ApiError::new(403, "forbidden".to_string()))
+    ///     })
+    ///     .into();
+    /// ```
+    ///
+    /// A request that cannot be parsed gets a 400 response with the error as
+    /// plain text.
+    pub struct Routes<RequestGroup = All, S = ()> {
+        router: Router<S>,
+        _p: PhantomData<RequestGroup>,
+    }
+
+    /// Explicitly implemented to avoid requirement RequestGroup: Debug
+    impl<RequestGroup, S> std::fmt::Debug for Routes<RequestGroup, S> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct(type_name::<Self>()).finish_non_exhaustive()
+        }
+    }
+
+    /// Explicitly implemented to avoid requirement RequestGroup: Default
+    impl<RequestGroup, S> Default for Routes<RequestGroup, S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        fn default() -> Self {
+            Self {
+                router: Router::new(),
+                _p: PhantomData,
+            }
+        }
+    }
+
+    impl<RequestGroup, S> Routes<RequestGroup, S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Respond to requests of type Req by calling the handler.
+        pub fn handle<Req, F, Fut>(mut self, handler: F) -> Self
+        where
+            Req: Route + InRequestGroup<RequestGroup> + Send + 'static,
+            Req::Serializer: DeserializeRequest<Req>,
+            Req::Deserializer: SerializeResponse<Req::Response>,
+            Req::Response: Send,
+            Req::ErrorResponse: serde::Serialize + Send,
+            F: Fn(Req) -> Fut + Clone + Send + Sync + 'static,
+            Fut: Future<Output = Result<Req::Response, ApiError<Req::ErrorResponse>>> + Send,
+        {
+            let route = move |params: Option<Path<Vec<(String, String)>>>,
+                              RawQuery(query): RawQuery,
+                              body: Bytes| async move {
+                let params = params.map(|Path(params)| params).unwrap_or_default();
+                let request =
+                    match <Req::Serializer as DeserializeRequest<Req>>::deserialize_request(
+                        &body,
+                        query.as_deref(),
+                        &params,
+                    ) {
+                        Ok(request) => request,
+                        Err(error) => {
+                            return (StatusCode::BAD_REQUEST, error.to_string()).into_response()
+                        }
+                    };
+                match handler(request).await {
+                    Ok(response) => respond::<Req::Deserializer, _>(StatusCode::OK, &response),
+                    Err(ApiError { status, body }) => {
+                        let status = StatusCode::from_u16(status)
+                            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+                        respond_error(status, &body)
+                    }
+                }
+            };
+            self.router = self
+                .router
+                .route(Req::PATH, on(method_filter(Req::METHOD), route));
+            self
+        }
+
+        pub fn into_router(self) -> Router<S> {
+            self.router
+        }
+    }
+
+    impl<RequestGroup, S> From<Routes<RequestGroup, S>> for Router<S> {
+        fn from(routes: Routes<RequestGroup, S>) -> Self {
+            routes.router
+        }
+    }
+
+    fn respond<Ser, T>(status: StatusCode, value: &T) -> Response
+    where
+        Ser: SerializeResponse<T>,
+    {
+        match Ser::serialize_response(value) {
+            Ok(body) => {
+                let mut response = (status, body).into_response();
+                match Ser::CONTENT_TYPE {
+                    Some(content_type) => response
+                        .headers_mut()
+                        .insert(CONTENT_TYPE, HeaderValue::from_static(content_type)),
+                    None => response.headers_mut().remove(CONTENT_TYPE),
+                };
+                response
+            }
+            Err(error) => (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()).into_response(),
+        }
+    }
+
+    /// The client reads an error body raw if the ErrorResponse is a string,
+    /// so a body that serializes to a json string is sent without quotes.
+    fn respond_error<E: serde::Serialize>(status: StatusCode, body: &E) -> Response {
+        let json = match serde_json::to_vec(body) {
+            Ok(json) => json,
+            Err(error) => {
+                return (StatusCode::INTERNAL_SERVER_ERROR, error.to_string()).into_response()
+            }
+        };
+        match serde_json::from_slice::<String>(&json) {
+            Ok(text) => respond::<crate::PlainText, _>(status, &text),
+            Err(_) => {
+                let mut response = (status, json).into_response();
+                response
+                    .headers_mut()
+                    .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+                response
+            }
+        }
+    }
+
+    fn method_filter(method: HttpMethod) -> MethodFilter {
+        match method {
+            HttpMethod::Options => MethodFilter::OPTIONS,
+            HttpMethod::Get => MethodFilter::GET,
+            HttpMethod::Post => MethodFilter::POST,
+            HttpMethod::Put => MethodFilter::PUT,
+            HttpMethod::Delete => MethodFilter::DELETE,
+            HttpMethod::Head => MethodFilter::HEAD,
+            HttpMethod::Trace => MethodFilter::TRACE,
+            HttpMethod::Connect => MethodFilter::CONNECT,
+            HttpMethod::Patch => MethodFilter::PATCH,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{HttpMethod, Path, Request, Route};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct GetUser {
+        id: String,
+    }
+
+    impl Request for GetUser {
+        type Serializer = NoBody;
+        type Deserializer = SerdeJson;
+        type Response = GetUser;
+        type ErrorResponse = String;
+
+        fn method(&self) -> HttpMethod {
+            HttpMethod::Get
+        }
+
+        fn path(&self) -> String {
+            Path::new("/users").segment(&self.id).into()
+        }
+    }
+
+    impl Route for GetUser {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/users/{id}";
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct ListPosts {
+        user: u32,
+        limit: u32,
+    }
+
+    impl Request for ListPosts {
+        type Serializer = SerdeQuery;
+        type Deserializer = SerdeJson;
+        type Response = Vec<String>;
+        type ErrorResponse = String;
+
+        fn method(&self) -> HttpMethod {
+            HttpMethod::Get
+        }
+
+        fn path(&self) -> String {
+            Path::new("/users")
+                .segment(self.user)
+                .segment("posts")
+                .into()
+        }
+    }
+
+    impl Route for ListPosts {
+        const METHOD: HttpMethod = HttpMethod::Get;
+        const PATH: &'static str = "/users/{user}/posts";
+    }
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn no_body_from_path_params() {
+        let request: GetUser =
+            NoBody::deserialize_request(b"", None, &params(&[("id", "a/b c&d=e")])).unwrap();
+        assert_eq!(request.id, "a/b c&d=e");
+    }
+
+    #[test]
+    fn query_prefers_query_over_path_params() {
+        let path = params(&[("user", "7")]);
+        let request: ListPosts =
+            SerdeQuery::deserialize_request(b"", Some("limit=2"), &path).unwrap();
+        assert_eq!(request, ListPosts { user: 7, limit: 2 });
+        let request: ListPosts =
+            SerdeQuery::deserialize_request(b"", Some("user=8&limit=2"), &path).unwrap();
+        assert_eq!(request, ListPosts { user: 8, limit: 2 });
+        let missing: Result<ListPosts, _> = SerdeQuery::deserialize_request(b"", None, &path);
+        assert!(missing.is_err());
+    }
+
+    #[cfg(feature = "axum")]
+    mod router {
+        use axum::{
+            body::{to_bytes, Body},
+            http::{self, StatusCode},
+            Router,
+        };
+        use tower::ServiceExt;
+
+        use super::*;
+        use crate::{ApiError, Routes};
+
+        fn app() -> Router {
+            Routes::<crate::All>::new()
+                .handle(|request: GetUser| async move {
+                    match request.id.as_str() {
+                        "missing" => Err(ApiError::new(404, "no such user".to_string())),
+                        _ => Ok(request),
+                    }
+                })
+                .handle(|request: ListPosts| async move {
+                    Ok((0..request.limit)
+                        .map(|n| format!("{}-{n}", request.user))
+                        .collect())
+                })
+                .into()
+        }
+
+        async fn call(method: &str, uri: &str) -> (StatusCode, Option<String>, String) {
+            let request = http::Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap();
+            let response = app().oneshot(request).await.unwrap();
+            let status = response.status();
+            let content_type = response
+                .headers()
+                .get(http::header::CONTENT_TYPE)
+                .map(|value| value.to_str().unwrap().to_owned());
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            (
+                status,
+                content_type,
+                String::from_utf8(body.to_vec()).unwrap(),
+            )
+        }
+
+        #[tokio::test]
+        async fn path_params_are_decoded() {
+            let path = GetUser { id: "a/b c".into() }.path();
+            assert_eq!(path, "/users/a%2Fb%20c");
+            let (status, content_type, body) = call("GET", &path).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(content_type.as_deref(), Some("application/json"));
+            assert_eq!(body, r#"{"id":"a/b c"}"#);
+        }
+
+        #[tokio::test]
+        async fn query_request_is_rebuilt_from_path_and_query() {
+            let request = ListPosts { user: 7, limit: 2 };
+            let query = serde_urlencoded::to_string([("limit", 2)]).unwrap();
+            let (status, _, body) = call("GET", &format!("{}?{query}", request.path())).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body, r#"["7-0","7-1"]"#);
+        }
+
+        #[tokio::test]
+        async fn unparsable_request_is_bad_request() {
+            let (status, _, _) = call("GET", "/users/x/posts?limit=2").await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            let (status, _, _) = call("GET", "/users/7/posts").await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+        }
+
+        #[tokio::test]
+        async fn string_api_error_is_plain_text() {
+            let (status, content_type, body) = call("GET", "/users/missing").await;
+            assert_eq!(status, StatusCode::NOT_FOUND);
+            assert_eq!(content_type.as_deref(), Some("text/plain; charset=utf-8"));
+            assert_eq!(body, "no such user");
+        }
+
+        #[tokio::test]
+        async fn other_methods_are_not_allowed() {
+            let (status, _, _) = call("DELETE", "/users/1").await;
+            assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+        }
+
+        /// Sends the client's requests to the router, without a network.
+        #[cfg(feature = "client-core")]
+        struct Server(Router);
+
+        #[cfg(feature = "client-core")]
+        #[async_trait::async_trait]
+        impl crate::Transport for Server {
+            async fn send(
+                &self,
+                request: crate::HttpRequest,
+            ) -> Result<crate::HttpResponse, crate::middleware::BoxError> {
+                let mut builder = http::Request::builder()
+                    .method(request.method.as_str())
+                    .uri(&request.url);
+                for (name, value) in &request.headers {
+                    builder = builder.header(name.as_str(), value.as_bytes());
+                }
+                let response = self
+                    .0
+                    .clone()
+                    .oneshot(builder.body(Body::from(request.body))?)
+                    .await?;
+                let status = ::http::StatusCode::from_u16(response.status().as_u16())?;
+                let mut headers = ::http::HeaderMap::new();
+                for (name, value) in response.headers() {
+                    headers.append(
+                        ::http::HeaderName::from_bytes(name.as_ref())?,
+                        ::http::HeaderValue::from_bytes(value.as_bytes())?,
+                    );
+                }
+                let body = to_bytes(response.into_body(), usize::MAX).await?;
+                Ok(crate::HttpResponse {
+                    status,
+                    headers,
+                    url: request.url,
+                    body: body.to_vec(),
+                })
+            }
+        }
+
+        #[cfg(feature = "client-core")]
+        #[tokio::test]
+        async fn client_round_trip() {
+            let client = crate::Client::<crate::All, _>::with_inner(
+                "http://localhost".into(),
+                Server(app()),
+            )
+            .unwrap();
+            let user = client.send(GetUser { id: "a/b".into() }).await.unwrap();
+            assert_eq!(user, GetUser { id: "a/b".into() });
+            let error = client
+                .send(GetUser {
+                    id: "missing".into(),
+                })
+                .await;
+            match error {
+                Err(crate::Error::Api { status, body, .. }) => {
+                    assert_eq!(status, 404);
+                    assert_eq!(body, "no such user");
+                }
+                other => panic!("unexpected result: {other:?}"),
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Helpers shared by the unit tests of the client modules.
+
+use std::sync::Mutex;
+
+use http::{HeaderMap, StatusCode};
+
+use crate::{
+    middleware::{BoxError, HttpRequest, HttpResponse},
+    HttpMethod, NoBody, PlainText, Request, Transport,
+};
+
+/// `GET /ping`, responding with plain text.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Ping;
+
+impl Request for Ping {
+    type Serializer = NoBody;
+    type Deserializer = PlainText;
+    type Response = String;
+    type ErrorResponse = String;
+
+    fn method(&self) -> HttpMethod {
+        HttpMethod::Get
+    }
+
+    fn path(&self) -> String {
+        "/ping".into()
+    }
+}
+
+type Handler = Box<dyn Fn(&HttpRequest) -> Result<HttpResponse, BoxError> + Send + Sync>;
+
+/// A transport that answers every request with the handler, and records the
+/// requests it was given.
+pub(crate) struct Stub {
+    handler: Handler,
+    pub(crate) requests: Mutex<Vec<HttpRequest>>,
+}
+
+impl Stub {
+    pub(crate) fn new(
+        handler: impl Fn(&HttpRequest) -> Result<HttpResponse, BoxError> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            handler: Box::new(handler),
+            requests: Mutex::new(vec![]),
+        }
+    }
+
+    /// Answers every request with status 200 and the body "pong".
+    pub(crate) fn ok() -> Self {
+        Self::new(|_| Ok(response(200, b"pong")))
+    }
+}
+
+#[async_trait::async_trait]
+impl Transport for Stub {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, BoxError> {
+        self.requests.lock().unwrap().push(request.clone());
+        (self.handler)(&request)
+    }
+}
+
+/// Lets tests keep a reference to the stub after giving it to a client.
+#[async_trait::async_trait]
+impl Transport for std::sync::Arc<Stub> {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, BoxError> {
+        Transport::send(&**self, request).await
+    }
+}
+
+pub(crate) fn response(status: u16, body: &[u8]) -> HttpResponse {
+    HttpResponse {
+        status: StatusCode::from_u16(status).unwrap(),
+        headers: HeaderMap::new(),
+        url: "http://example.com/".into(),
+        body: body.to_vec(),
+    }
+}
+
+/// Answers one request on a local port with "pong", and returns the base url
+/// and the request line and headers it received, in lowercase. For testing
+/// the reqwest transport, which a `Stub` replaces.
+#[cfg(feature = "client")]
+pub(crate) fn serve_once() -> (String, std::thread::JoinHandle<String>) {
+    use std::io::{BufRead, BufReader, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut head = String::new();
+        let mut reader = BufReader::new(&stream);
+        while reader.read_line(&mut head).unwrap() > 2 {}
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 4\r\nconnection: close\r\n\r\npong")
+            .unwrap();
+        head.to_lowercase()
+    });
+    (base_url, server)
+}
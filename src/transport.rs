@@ -0,0 +1,81 @@
+use crate::middleware::{BoxError, HttpRequest, HttpResponse};
+
+/// Sends serialized http requests for a Client. The `client` feature provides
+/// an implementation for `reqwest::Client`, which is the default transport.
+/// Implement this trait to use a different http library, or to send requests
+/// some other way entirely.
+///
+/// ```ignore
+/// struct MyTransport(my_http::Client);
+///
+/// #[async_trait::async_trait]
+/// impl Transport for MyTransport {
+///     async fn send(&self, request: HttpRequest) -> Result<HttpResponse, BoxError> {
+///         let response = self.0.call(request.method, &request.url, request.body).await?;
+///         Ok(HttpResponse {
+///             status: response.status,
+///             headers: response.headers,
+///             url: request.url,
+///             body: response.body,
+///         })
+///     }
+/// }
+///
+/// let client = Client::<MyApi, _>::with_inner("http://example.com".into(), MyTransport::new())?;
+/// ```
+#[async_trait::async_trait]
+pub trait Transport: Send + Sync + 'static {
+    /// Send the request and receive the entire response body.
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, BoxError>;
+
+    /// Whether an error returned by `send` is transient, meaning the request
+    /// may succeed if it is retried. For example, a connection failure or
+    /// timeout. Defaults to false.
+    fn is_transient(&self, error: &BoxError) -> bool {
+        let _ = error;
+        false
+    }
+}
+
+/// Placeholder for the default transport when the `client` feature is
+/// disabled. It cannot be instantiated, so the transport must be specified
+/// explicitly.
+#[cfg(not(feature = "client"))]
+#[derive(Debug, Clone, Copy)]
+pub enum NoTransport {}
+
+/// The transport used by a Client unless another is specified.
+#[cfg(feature = "client")]
+pub type DefaultTransport = reqwest::Client;
+#[cfg(not(feature = "client"))]
+pub type DefaultTransport = NoTransport;
+
+#[cfg(feature = "client")]
+#[async_trait::async_trait]
+impl Transport for reqwest::Client {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, BoxError> {
+        let response = self
+            .request(request.method.into(), &request.url)
+            .headers(request.headers)
+            .body(request.body)
+            .send()
+            .await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let url = response.url().to_string();
+        let body = response.bytes().await?.to_vec();
+        Ok(HttpResponse {
+            status,
+            headers,
+            url,
+            body,
+        })
+    }
+
+    fn is_transient(&self, error: &BoxError) -> bool {
+        match error.downcast_ref::<reqwest::Error>() {
+            Some(error) => error.is_connect() || error.is_timeout() || error.is_request(),
+            None => false,
+        }
+    }
+}